@@ -1,3 +1,5 @@
 fn main() {
+    // 不带界面构建命令行客户端时无需 Tauri 资源与 sidecar
+    #[cfg(feature = "gui")]
     tauri_build::build()
 }
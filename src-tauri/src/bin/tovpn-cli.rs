@@ -0,0 +1,5 @@
+//! 无界面命令行客户端入口
+
+fn main() -> std::process::ExitCode {
+    tovpn::cli::main()
+}
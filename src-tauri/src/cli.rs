@@ -0,0 +1,401 @@
+//! 命令行客户端模块
+//! `tovpn-cli` 复用 vpn 模块的配置生成、签名、平台实现与连通性测试，在没有界面的 CI / 服务器上
+//! 建立同样的隧道。每个命令向标准输出写一行 JSON，失败时为结构化错误并以非零状态退出。
+//! sing-box 在后台持续运行，会话信息写入按用户区分的运行时目录供 status / disconnect 读取；不安装 kill switch
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, ExitCode, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::constants::{self, DEFAULT_SOCKS_PORT};
+use crate::error::{ErrorPayload, Result, VpnError};
+use crate::single_instance;
+use crate::vpn::clash_api::{fetch_traffic_from_api, ClashApi};
+use crate::vpn::config::ConnectConfig;
+use crate::vpn::connectivity::{self, is_port_free, verify_tunnel, wait_for_port_ready};
+use crate::vpn::platform::{self, NetworkSnapshot};
+use crate::vpn::singbox::{self, diagnose};
+use crate::vpn::{mtu, ping, security, teardown};
+
+const USAGE: &str = "usage: tovpn-cli <command>
+  connect --config <file.json> [--mode socks|tun]
+  disconnect
+  status
+  ping <host:port>...
+  diagnose";
+
+/// 命令行会话（含连接前的网络快照，断开时据此校验清理结果）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CliSession {
+    pid: Option<u32>,
+    mode: String,
+    server: String,
+    clash_api: ClashApi,
+    /// 连接建立时间 (秒)
+    started_at: u64,
+    baseline: NetworkSnapshot,
+}
+
+impl CliSession {
+    fn load() -> Option<Self> {
+        fs::read_to_string(constants::get_cli_session_file())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = constants::get_cli_session_file();
+        let json = serde_json::to_string(self)
+            .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
+        security::write_private(&path, json.as_bytes())
+    }
+
+    fn clear() {
+        let _ = fs::remove_file(constants::get_cli_session_file());
+    }
+}
+
+/// 命令行建立的隧道仍在运行；桌面应用据此避免在启动、退出与连接时清理它
+pub(crate) fn owns_tunnel() -> bool {
+    CliSession::load().is_some() && platform::owned::any_alive()
+}
+
+/// 在单线程运行时上执行异步探测；命令行客户端不依赖 Tauri 的运行时
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build tokio runtime")
+        .block_on(future)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// 与桌面应用相同的应用数据目录下单独的子目录，避免覆盖界面生成的配置
+fn data_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join(constants::APP_IDENTIFIER).join("cli"))
+        .ok_or_else(|| VpnError::Io("App data dir unavailable".to_string()))
+}
+
+/// 命令行入口，返回进程退出码
+pub fn main() -> ExitCode {
+    crate::logging::init_stderr();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("connect") => connect(&args[1..]),
+        Some("disconnect") => disconnect(),
+        Some("status") => status(),
+        Some("ping") => ping_targets(&args[1..]),
+        Some("diagnose") => diagnose(),
+        _ => Err(VpnError::Config(USAGE.to_string())),
+    };
+
+    match result {
+        Ok(value) => {
+            println!("{}", json!({ "ok": true, "result": value }));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("{}", json!({ "ok": false, "error": ErrorPayload::from(e) }));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn connect(args: &[String]) -> Result<Value> {
+    let mut config_file = None;
+    let mut mode = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => config_file = iter.next(),
+            "--mode" => mode = iter.next(),
+            other => return Err(VpnError::Config(format!("Unknown option '{}'", other))),
+        }
+    }
+    let config_file =
+        config_file.ok_or_else(|| VpnError::Config("--config <file.json> is required".into()))?;
+    let mut config: ConnectConfig = serde_json::from_str(&fs::read_to_string(config_file)?)
+        .map_err(|e| VpnError::Config(format!("Invalid config file: {}", e)))?;
+    if let Some(mode) = mode {
+        config.mode = mode.clone();
+    }
+    config.validate()?;
+
+    // 桌面应用连接前的清理会结束本机所有登记的 sing-box
+    if single_instance::is_running() {
+        return Err(VpnError::DesktopAppRunning(
+            "connect from the desktop app or quit it first".to_string(),
+        ));
+    }
+    if platform::owned::any_alive() {
+        return Err(VpnError::AlreadyConnected);
+    }
+
+    let dir = data_dir()?;
+    let baseline = platform::network_snapshot();
+
+    if config.mode == "tun" && config.mtu == 0 {
+        config.mtu = mtu::resolve_tun_mtu(&dir, &config.server_host, config.server_port)
+            .map(|m| m.tun_mtu)
+            .unwrap_or(constants::MTU_MAX);
+    }
+
+    let api = ClashApi::generate()?;
+    let config_path = singbox::write_config(&dir, &config, &api)?;
+    security::sign_config(&config_path)?;
+    let config_path = config_path
+        .to_str()
+        .ok_or(VpnError::Config("Invalid path".into()))?;
+
+    info!(mode = %config.mode, server = %config.server_host, "Connecting");
+    let pid = if config.mode == "tun" {
+        start_tun(config_path)?
+    } else {
+        start_socks(config_path)?
+    };
+
+    let latency_ms = match block_on(verify_tunnel(&api, &CancellationToken::new())) {
+        Ok(latency) => latency,
+        Err(e) => {
+            // sing-box 输出中的出站错误比延迟测试的结果更具体
+            let diagnosis = if config.mode == "tun" {
+                diagnose::diagnose_tun_log()
+            } else {
+                read_socks_log().and_then(|output| diagnose::classify(&output))
+            };
+            let _ = teardown::run(&config.mode, Some(&baseline));
+            return Err(diagnosis.map(|d| d.to_error()).unwrap_or(e));
+        }
+    };
+
+    let session = CliSession {
+        pid,
+        mode: config.mode.clone(),
+        server: format!("{}:{}", config.server_host, config.server_port),
+        clash_api: api,
+        started_at: now_secs(),
+        baseline,
+    };
+    // 没有会话文件的隧道无法再由 status / disconnect 管理，保存失败时拆除
+    if let Err(e) = session.save() {
+        let _ = teardown::run(&session.mode, Some(&session.baseline));
+        return Err(e);
+    }
+
+    Ok(json!({
+        "status": "connected",
+        "mode": session.mode,
+        "server": session.server,
+        "pid": session.pid,
+        "latency_ms": latency_ms,
+        "socks_port": (session.mode == "socks").then_some(DEFAULT_SOCKS_PORT),
+    }))
+}
+
+/// 提权启动 TUN 模式的 sing-box
+fn start_tun(config_path: &str) -> Result<Option<u32>> {
+    if !platform::check_singbox_installed() {
+        return Err(VpnError::BinaryMissing(
+            "sing-box not found in system path".to_string(),
+        ));
+    }
+    let log_path = diagnose::tun_log_path().to_string_lossy().to_string();
+    if let Err(msg) = platform::run_singbox_tun_as_root(config_path, &log_path) {
+        let diagnosis = diagnose::classify(&msg).or_else(diagnose::diagnose_tun_log);
+        return Err(diagnosis
+            .map(|d| d.to_error())
+            .unwrap_or(VpnError::Connection(msg)));
+    }
+    Ok(platform::owned::alive().first().map(|p| p.pid))
+}
+
+/// 在后台启动 SOCKS 模式的 sing-box，输出写入日志文件
+fn start_socks(config_path: &str) -> Result<Option<u32>> {
+    if !block_on(is_port_free(DEFAULT_SOCKS_PORT)) {
+        return Err(VpnError::PortInUse(format!(
+            "127.0.0.1:{} is held by another process",
+            DEFAULT_SOCKS_PORT
+        )));
+    }
+
+    let log = fs::File::create(constants::get_cli_log_file())?;
+    let mut command = Command::new(singbox::sidecar_path());
+    command
+        .args(["run", "-c", config_path])
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // 脱离终端的进程组，命令行退出或在终端按 Ctrl-C 不影响隧道
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+    let child = command
+        .spawn()
+        .map_err(|e| VpnError::BinaryMissing(format!("Spawn error: {}", e)))?;
+    platform::owned::register(child.id(), false);
+
+    let ready = block_on(wait_for_port_ready(
+        DEFAULT_SOCKS_PORT,
+        8000,
+        &CancellationToken::new(),
+    ));
+    if !ready {
+        let output = read_socks_log().unwrap_or_default();
+        let _ = teardown::run("socks", None);
+        return Err(diagnose::classify(&output)
+            .map(|d| d.to_error())
+            .unwrap_or(VpnError::Connection("SOCKS port not ready".to_string())));
+    }
+    Ok(Some(child.id()))
+}
+
+fn read_socks_log() -> Option<String> {
+    fs::read_to_string(constants::get_cli_log_file()).ok()
+}
+
+fn disconnect() -> Result<Value> {
+    let Some(session) = CliSession::load() else {
+        if platform::owned::any_alive() {
+            return Err(VpnError::TunnelOwnedElsewhere(
+                "the running tunnel was started by the desktop app".to_string(),
+            ));
+        }
+        return Ok(json!({ "status": "disconnected" }));
+    };
+
+    let report = teardown::run(&session.mode, Some(&session.baseline));
+    CliSession::clear();
+    if !report.is_clean() {
+        warn!(issues = ?report.issues, "Cleanup left resources behind");
+    }
+    Ok(json!({ "status": "disconnected", "cleanup": report }))
+}
+
+fn status() -> Result<Value> {
+    let Some(session) = CliSession::load() else {
+        let status = if platform::owned::any_alive() {
+            "desktop_app"
+        } else {
+            "disconnected"
+        };
+        return Ok(json!({ "status": status }));
+    };
+
+    let base = json!({
+        "mode": session.mode,
+        "server": session.server,
+        "pid": session.pid,
+        "uptime_secs": now_secs().saturating_sub(session.started_at),
+    });
+    if !platform::owned::any_alive() {
+        // 进程意外退出；disconnect 负责清理残留
+        return Ok(json!({ "status": "failed", "detail": "sing-box exited", "session": base }));
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .ok();
+    let traffic = fetch_traffic_from_api(&client, Some(&session.clash_api)).map(
+        |(download_total, upload_total)| {
+            json!({ "download_total": download_total, "upload_total": upload_total })
+        },
+    );
+    Ok(json!({ "status": "connected", "session": base, "traffic": traffic }))
+}
+
+/// `host:port`，IPv6 地址写作 `[::1]:443`
+fn parse_target(target: &str) -> Result<(String, u16)> {
+    let invalid = || VpnError::InvalidServer(format!("Expected host:port, got '{}'", target));
+    let (host, port) = target.rsplit_once(':').ok_or_else(invalid)?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = port.parse().map_err(|_| invalid())?;
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok((host.to_string(), port))
+}
+
+fn ping_targets(targets: &[String]) -> Result<Value> {
+    if targets.is_empty() {
+        return Err(VpnError::Config(USAGE.to_string()));
+    }
+    let parsed = targets
+        .iter()
+        .map(|t| parse_target(t))
+        .collect::<Result<Vec<_>>>()?;
+
+    let handles: Vec<_> = parsed
+        .into_iter()
+        .enumerate()
+        .map(|(index, (host, port))| {
+            std::thread::spawn(move || ping::ping_node(index as i32, &host, port))
+        })
+        .collect();
+    let results: Vec<Value> = handles
+        .into_iter()
+        .zip(targets)
+        .filter_map(|(handle, target)| {
+            let result = handle.join().ok()?;
+            Some(json!({
+                "target": target,
+                "latency_ms": (result.latency_ms >= 0).then_some(result.latency_ms),
+                "status": result.status,
+            }))
+        })
+        .collect();
+    Ok(json!(results))
+}
+
+fn diagnose() -> Result<Value> {
+    let session = CliSession::load();
+    let running = session.is_some() && platform::owned::any_alive();
+    let socks = running && session.as_ref().is_some_and(|s| s.mode == "socks");
+
+    let (direct, proxy, dns) = block_on(async {
+        (
+            connectivity::test_connectivity(false).await,
+            if socks {
+                Some(connectivity::test_connectivity(true).await)
+            } else {
+                None
+            },
+            connectivity::test_dns_resolution("www.google.com".to_string()).await,
+        )
+    });
+    let last_failure = match session.as_ref().map(|s| s.mode.as_str()) {
+        Some("tun") => diagnose::diagnose_tun_log(),
+        _ => read_socks_log().and_then(|output| diagnose::classify(&output)),
+    };
+
+    Ok(json!({
+        "connected": running,
+        "singbox_installed": singbox::sidecar_path().is_file() || platform::check_singbox_installed(),
+        "direct": direct,
+        "proxy": proxy,
+        "dns": dns,
+        "last_failure": last_failure,
+    }))
+}
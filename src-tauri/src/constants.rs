@@ -21,19 +21,62 @@ pub fn get_singbox_pid_file() -> PathBuf {
     get_cache_dir().join("tovpn-singbox.pid")
 }
 
+/// 本应用启动的 sing-box 进程登记文件；驱动提权结束进程，放在按用户区分的目录
+pub fn get_owned_process_file() -> PathBuf {
+    get_user_runtime_dir().join("tovpn-owned.json")
+}
+
+/// 会话元数据文件 (界面重启后接管运行中的会话)；含密钥，放在按用户区分的目录
+pub fn get_session_file() -> PathBuf {
+    get_user_runtime_dir().join("tovpn-session.json")
+}
+
+/// 命令行客户端的会话文件 (status/disconnect 读取)；含 Clash API secret，放在按用户区分的目录
+pub fn get_cli_session_file() -> PathBuf {
+    get_user_runtime_dir().join("tovpn-cli-session.json")
+}
+
+/// 命令行客户端 SOCKS 模式下 sing-box 的输出
+pub fn get_cli_log_file() -> PathBuf {
+    get_cache_dir().join("tovpn-cli-singbox.log")
+}
+
+/// 按用户区分的运行时目录：优先 XDG_RUNTIME_DIR (仅当前用户可访问)，否则为缓存目录
+pub fn get_user_runtime_dir() -> PathBuf {
+    #[cfg(unix)]
+    {
+        dirs::runtime_dir()
+            .unwrap_or_else(|| get_cache_dir().join(format!("tovpn-{}", unsafe { libc::getuid() })))
+    }
+    #[cfg(not(unix))]
+    {
+        get_cache_dir()
+    }
+}
+
+/// 单实例锁文件
+pub fn get_instance_lock_file() -> PathBuf {
+    get_user_runtime_dir().join("tovpn-instance.lock")
+}
+
+/// 单实例激活通道：unix 下为套接字路径，Windows 下为记录回环端口的文件
+pub fn get_instance_socket() -> PathBuf {
+    get_user_runtime_dir().join("tovpn-instance.sock")
+}
+
 /// TUN 模式标记文件 (用于辅助清理)
 pub fn get_tun_lock_file() -> PathBuf {
     get_cache_dir().join("tovpn-tun.lock")
 }
 
+/// 应用标识 (与 tauri.conf.json 一致)，命令行客户端据此定位应用数据目录
+pub const APP_IDENTIFIER: &str = "com.tovpn.client";
+
 /// 默认 SOCKS 代理端口
 pub const DEFAULT_SOCKS_PORT: u16 = 1080;
 
-/// TUN 模式专用的 API 端口
-pub const SINGBOX_API_PORT_TUN: u16 = 9090;
-
-/// SOCKS 模式专用的 API 端口
-pub const SINGBOX_API_PORT_SOCKS: u16 = 9091;
+/// Clash API secret 随机字节数 (hex 编码后长度翻倍)
+pub const CLASH_API_SECRET_BYTES: usize = 32;
 
 /// 默认 MTU 最大值
 pub const MTU_MAX: u16 = 1500;
@@ -44,6 +87,12 @@ pub const MONITOR_INTERVAL_MS: u64 = 1000;
 /// 端口检查超时（毫秒）
 pub const PORT_CHECK_TIMEOUT_MS: u64 = 100;
 
+/// 连接建立前经隧道访问的测试地址
+pub const TUNNEL_TEST_URL: &str = "http://www.gstatic.com/generate_204";
+
+/// 隧道验证请求超时（毫秒）
+pub const TUNNEL_VERIFY_TIMEOUT_MS: u64 = 5000;
+
 /// DNS 服务器配置
 pub mod dns {
     pub const GOOGLE_DOH: &str = "https://dns.google/dns-query";
@@ -56,4 +105,93 @@ pub mod dns {
 pub mod tun {
     pub const IPV4_ADDRESS: &str = "172.19.0.1/30";
     pub const IPV6_ADDRESS: &str = "fdfe::1/126";
+    /// Linux 下固定 TUN 网卡名，kill switch 按网卡放行
+    pub const INTERFACE_NAME: &str = "tovpn0";
+    /// 等待提权认证并建立 TUN 网卡的时限 (秒)，需覆盖用户输入密码的时间
+    pub const START_TIMEOUT_SECS: u64 = 120;
+}
+
+/// Kill switch (nftables) 参数
+pub mod kill_switch {
+    pub const TABLE: &str = "tovpn_killswitch";
+    /// sing-box 出站连接的 SO_MARK，带此标记的流量 (直连分流、本地 DNS) 放行
+    pub const BYPASS_MARK: u32 = 0x7476;
+    /// 局域网放行网段 (含 DHCP 广播与组播)
+    pub const LAN_V4: [&str; 6] = [
+        "10.0.0.0/8",
+        "172.16.0.0/12",
+        "192.168.0.0/16",
+        "169.254.0.0/16",
+        "224.0.0.0/4",
+        "255.255.255.255/32",
+    ];
+    pub const LAN_V6: [&str; 3] = ["fe80::/10", "fc00::/7", "ff00::/8"];
+    /// 备选节点与上游代理的解析总时限，超时未解析的地址不放行
+    pub const RESOLVE_TIMEOUT_MS: u64 = 3000;
+}
+
+/// Path-MTU 探测参数
+pub mod mtu {
+    /// IPv4 头 + UDP 头
+    pub const IP_UDP_HEADER: u16 = 28;
+    /// QUIC short header (flags + 8 字节连接 ID + 包号) + AEAD tag
+    pub const QUIC_OVERHEAD: u16 = 29;
+    /// hysteria2 UDP 报文头 (session/packet/fragment ID + 目标地址)
+    pub const HYSTERIA_OVERHEAD: u16 = 23;
+    /// 探测下限：QUIC 要求 UDP 负载至少 1200 字节，服务器只应答不小于该尺寸的探测包
+    pub const PROBE_FLOOR: u16 = 1200 + IP_UDP_HEADER;
+    /// 二分查找的精度
+    pub const PROBE_STEP: u16 = 8;
+    /// TUN MTU 下限，与 ConnectConfig 校验一致
+    pub const TUN_MIN: u16 = 576;
+    /// 每个尺寸的发送次数 (容忍丢包) / 每次等待应答的时间
+    pub const PROBE_ATTEMPTS: u32 = 2;
+    pub const PROBE_WAIT_MS: u64 = 250;
+    /// 缓存有效期 (秒)
+    pub const CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+}
+
+/// 自动重连默认策略
+pub mod reconnect {
+    pub const MAX_ATTEMPTS: u32 = 5;
+    pub const BASE_DELAY_MS: u64 = 1000;
+    pub const MAX_DELAY_MS: u64 = 30_000;
+}
+
+/// 断开时结束 sing-box 的等待时长
+pub mod teardown {
+    /// SIGTERM 后等待进程自行退出 (毫秒)
+    pub const STOP_GRACE_MS: u64 = 3000;
+    /// SIGKILL 后等待进程消失 (毫秒)
+    pub const KILL_WAIT_MS: u64 = 1000;
+}
+
+/// 网络变化处理参数
+pub mod netwatch {
+    /// 最后一个事件后静默多久才处理，合并切换过程中的连串事件
+    pub const SETTLE_MS: u64 = 2000;
+    /// 处理完成后忽略事件的时长，避免重连本身引起的变化再次触发
+    pub const COOLDOWN_SECS: u64 = 10;
+}
+
+/// 本地控制接口
+pub mod control {
+    /// 应用数据目录下的控制套接字文件名
+    pub const SOCKET_FILE: &str = "control.sock";
+    /// 每个订阅者可积压的事件数，超出后丢弃最旧的事件
+    pub const EVENT_BUFFER: usize = 256;
+    /// 每个客户端待写出的消息数；订阅者停止读取导致积压满时断开该客户端
+    pub const CLIENT_QUEUE: usize = 64;
+}
+
+/// sing-box 崩溃循环保护
+pub mod crash_loop {
+    /// 统计窗口 (秒)
+    pub const WINDOW_SECS: u64 = 120;
+    /// 窗口内同一节点退出达到该次数即判定为崩溃循环
+    pub const THRESHOLD: usize = 3;
+    /// 节点被标记为故障的时长 (秒)，期间自动连接与重连跳过该节点
+    pub const FAILING_SECS: u64 = 600;
+    /// 保留的退出记录条数
+    pub const HISTORY_LIMIT: usize = 32;
 }
@@ -5,7 +5,6 @@ use thiserror::Error;
 
 /// VPN 操作错误类型
 #[derive(Debug, Error, Serialize, Clone)]
-#[allow(dead_code)]
 pub enum VpnError {
     #[error("Configuration error: {0}")]
     Config(String),
@@ -28,11 +27,41 @@ pub enum VpnError {
     #[error("IO error: {0}")]
     Io(String),
 
-    #[error("DNS leak detected: {0}")]
-    DnsLeak(String),
+    #[error("Invalid state transition: {0}")]
+    InvalidTransition(String),
 
-    #[error("OAuth callback failed: {0}")]
-    OAuthCallback(String),
+    #[error("Kill switch error: {0}")]
+    KillSwitch(String),
+
+    #[error("Connection cancelled")]
+    Cancelled,
+
+    #[error("Port in use: {0}")]
+    PortInUse(String),
+
+    #[error("Authentication rejected: {0}")]
+    AuthRejected(String),
+
+    #[error("TLS failure: {0}")]
+    TlsFailure(String),
+
+    #[error("UDP blocked: {0}")]
+    UdpBlocked(String),
+
+    #[error("Privilege denied: {0}")]
+    PrivilegeDenied(String),
+
+    #[error("sing-box binary missing: {0}")]
+    BinaryMissing(String),
+
+    #[error("Crash loop detected: {0}")]
+    CrashLoop(String),
+
+    #[error("Desktop app is running: {0}")]
+    DesktopAppRunning(String),
+
+    #[error("Tunnel owned by another client: {0}")]
+    TunnelOwnedElsewhere(String),
 }
 
 impl From<std::io::Error> for VpnError {
@@ -49,6 +78,30 @@ impl From<reqwest::Error> for VpnError {
 
 pub type Result<T> = std::result::Result<T, VpnError>;
 
+/// 返回给前端的结构化错误
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorPayload {
+    /// 稳定错误码，前端据此分支处理，不随文案变化
+    pub code: &'static str,
+    /// config / connection / network / permission / system / state
+    pub category: &'static str,
+    /// 用户可读提示（同 `user_message`）
+    pub message: String,
+    /// 技术细节，用于日志与问题反馈
+    pub detail: Option<String>,
+    /// 建议的处理方式
+    pub action: Option<&'static str>,
+}
+
+impl From<VpnError> for ErrorPayload {
+    fn from(err: VpnError) -> Self {
+        err.payload()
+    }
+}
+
+/// 返回结构化错误的 Tauri 命令结果
+pub type CommandResult<T> = std::result::Result<T, ErrorPayload>;
+
 impl VpnError {
     /// 用户友好的错误提示
     pub fn user_message(&self) -> String {
@@ -60,8 +113,249 @@ impl VpnError {
             VpnError::InvalidServer(_) => "无效的服务器配置 / Invalid Server".to_string(),
             VpnError::Network(_) => "网络错误 / Network Error".to_string(),
             VpnError::Io(_) => "系统内部错误 / System Error".to_string(),
-            VpnError::DnsLeak(_) => "DNS泄漏 / DNS Leak Detected".to_string(),
-            VpnError::OAuthCallback(_) => "认证回调失败 / OAuth Failed".to_string(),
+            VpnError::InvalidTransition(_) => {
+                "操作与当前状态冲突 / Operation Not Allowed".to_string()
+            }
+            VpnError::KillSwitch(_) => "断网保护设置失败 / Kill Switch Failed".to_string(),
+            VpnError::Cancelled => "连接已取消 / Connection Cancelled".to_string(),
+            VpnError::PortInUse(_) => "端口被占用 / Port In Use".to_string(),
+            VpnError::AuthRejected(_) => "节点认证失败 / Authentication Rejected".to_string(),
+            VpnError::TlsFailure(_) => "TLS 握手失败 / TLS Handshake Failed".to_string(),
+            VpnError::UdpBlocked(_) => "UDP 流量被阻断 / UDP Blocked".to_string(),
+            VpnError::PrivilegeDenied(_) => "未获得管理员授权 / Privilege Denied".to_string(),
+            VpnError::BinaryMissing(_) => "未找到 sing-box / sing-box Not Found".to_string(),
+            VpnError::CrashLoop(_) => "节点反复异常退出 / Crash Loop Detected".to_string(),
+            VpnError::DesktopAppRunning(_) => "桌面应用正在运行 / Desktop App Running".to_string(),
+            VpnError::TunnelOwnedElsewhere(_) => {
+                "隧道由其他客户端建立 / Tunnel Owned by Another Client".to_string()
+            }
+        }
+    }
+
+    /// 稳定错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            VpnError::Config(_) => "CONFIG_INVALID",
+            VpnError::Connection(_) => "CONNECTION_FAILED",
+            VpnError::AlreadyConnected => "ALREADY_CONNECTED",
+            VpnError::AlreadyConnecting => "ALREADY_CONNECTING",
+            VpnError::InvalidServer(_) => "INVALID_SERVER",
+            VpnError::Network(_) => "NETWORK_ERROR",
+            VpnError::Io(_) => "IO_ERROR",
+            VpnError::InvalidTransition(_) => "INVALID_TRANSITION",
+            VpnError::KillSwitch(_) => "KILL_SWITCH_FAILED",
+            VpnError::Cancelled => "CANCELLED",
+            VpnError::PortInUse(_) => "PORT_IN_USE",
+            VpnError::AuthRejected(_) => "AUTH_REJECTED",
+            VpnError::TlsFailure(_) => "TLS_FAILURE",
+            VpnError::UdpBlocked(_) => "UDP_BLOCKED",
+            VpnError::PrivilegeDenied(_) => "PRIVILEGE_DENIED",
+            VpnError::BinaryMissing(_) => "BINARY_MISSING",
+            VpnError::CrashLoop(_) => "CRASH_LOOP",
+            VpnError::DesktopAppRunning(_) => "DESKTOP_APP_RUNNING",
+            VpnError::TunnelOwnedElsewhere(_) => "TUNNEL_OWNED_ELSEWHERE",
+        }
+    }
+
+    pub fn category(&self) -> &'static str {
+        match self {
+            VpnError::Config(_) | VpnError::InvalidServer(_) => "config",
+            VpnError::Connection(_)
+            | VpnError::AuthRejected(_)
+            | VpnError::TlsFailure(_)
+            | VpnError::UdpBlocked(_)
+            | VpnError::CrashLoop(_) => "connection",
+            VpnError::Network(_) => "network",
+            VpnError::PrivilegeDenied(_) | VpnError::KillSwitch(_) => "permission",
+            VpnError::Io(_) | VpnError::PortInUse(_) | VpnError::BinaryMissing(_) => "system",
+            VpnError::AlreadyConnected
+            | VpnError::AlreadyConnecting
+            | VpnError::InvalidTransition(_)
+            | VpnError::Cancelled
+            | VpnError::DesktopAppRunning(_)
+            | VpnError::TunnelOwnedElsewhere(_) => "state",
+        }
+    }
+
+    /// 建议操作（中英双语，与 `user_message` 一致）；无明确建议时为 None
+    pub fn action(&self) -> Option<&'static str> {
+        match self {
+            VpnError::PortInUse(_) => Some(
+                "关闭占用本地代理端口的程序后重试 / Close the program using the local proxy port and retry",
+            ),
+            VpnError::AuthRejected(_) => Some(
+                "刷新节点列表或检查订阅是否过期 / Refresh the server list or check whether your subscription has expired",
+            ),
+            VpnError::TlsFailure(_) => Some(
+                "检查系统时间，或切换其他节点 / Check the system clock, or switch to another server",
+            ),
+            VpnError::UdpBlocked(_) => Some(
+                "当前网络可能封锁 UDP，请切换网络或节点 / This network may block UDP, switch networks or servers",
+            ),
+            VpnError::PrivilegeDenied(_) => Some(
+                "在授权对话框中输入管理员密码 / Enter the administrator password in the authorization dialog",
+            ),
+            VpnError::BinaryMissing(_) => Some(
+                "重新安装应用或安装 sing-box / Reinstall the app or install sing-box",
+            ),
+            VpnError::CrashLoop(_) => Some(
+                "已停止自动重连，请切换其他节点，或检查节点配置后手动连接 / Auto-reconnect stopped, switch to another server or check its configuration and connect manually",
+            ),
+            VpnError::KillSwitch(_) => Some(
+                "检查 nftables 是否可用，或关闭断网保护 / Check that nftables is available, or turn off the kill switch",
+            ),
+            VpnError::DesktopAppRunning(_) => Some(
+                "在桌面应用中操作，或先退出桌面应用 / Use the desktop app, or quit it first",
+            ),
+            VpnError::TunnelOwnedElsewhere(_) => Some(
+                "在建立该隧道的客户端中断开后重试 / Disconnect from the client that started the tunnel and retry",
+            ),
+            VpnError::Network(_) | VpnError::Connection(_) => {
+                Some("检查网络连接后重试 / Check your network connection and retry")
+            }
+            _ => None,
+        }
+    }
+
+    /// 变体携带的技术细节
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            VpnError::Config(d)
+            | VpnError::Connection(d)
+            | VpnError::InvalidServer(d)
+            | VpnError::Network(d)
+            | VpnError::Io(d)
+            | VpnError::InvalidTransition(d)
+            | VpnError::KillSwitch(d)
+            | VpnError::PortInUse(d)
+            | VpnError::AuthRejected(d)
+            | VpnError::TlsFailure(d)
+            | VpnError::UdpBlocked(d)
+            | VpnError::PrivilegeDenied(d)
+            | VpnError::BinaryMissing(d)
+            | VpnError::CrashLoop(d)
+            | VpnError::DesktopAppRunning(d)
+            | VpnError::TunnelOwnedElsewhere(d) => Some(d.clone()),
+            VpnError::AlreadyConnected | VpnError::AlreadyConnecting | VpnError::Cancelled => None,
+        }
+    }
+
+    pub fn payload(&self) -> ErrorPayload {
+        ErrorPayload {
+            code: self.code(),
+            category: self.category(),
+            message: self.user_message(),
+            detail: self.detail(),
+            action: self.action(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// 错误码与分类是前端、控制接口与命令行共用的契约
+    fn table() -> Vec<(VpnError, &'static str, &'static str)> {
+        let d = || "detail".to_string();
+        vec![
+            (VpnError::Config(d()), "CONFIG_INVALID", "config"),
+            (VpnError::InvalidServer(d()), "INVALID_SERVER", "config"),
+            (VpnError::Connection(d()), "CONNECTION_FAILED", "connection"),
+            (VpnError::AuthRejected(d()), "AUTH_REJECTED", "connection"),
+            (VpnError::TlsFailure(d()), "TLS_FAILURE", "connection"),
+            (VpnError::UdpBlocked(d()), "UDP_BLOCKED", "connection"),
+            (VpnError::CrashLoop(d()), "CRASH_LOOP", "connection"),
+            (VpnError::Network(d()), "NETWORK_ERROR", "network"),
+            (
+                VpnError::PrivilegeDenied(d()),
+                "PRIVILEGE_DENIED",
+                "permission",
+            ),
+            (
+                VpnError::KillSwitch(d()),
+                "KILL_SWITCH_FAILED",
+                "permission",
+            ),
+            (VpnError::Io(d()), "IO_ERROR", "system"),
+            (VpnError::PortInUse(d()), "PORT_IN_USE", "system"),
+            (VpnError::BinaryMissing(d()), "BINARY_MISSING", "system"),
+            (VpnError::AlreadyConnected, "ALREADY_CONNECTED", "state"),
+            (VpnError::AlreadyConnecting, "ALREADY_CONNECTING", "state"),
+            (
+                VpnError::InvalidTransition(d()),
+                "INVALID_TRANSITION",
+                "state",
+            ),
+            (VpnError::Cancelled, "CANCELLED", "state"),
+            (
+                VpnError::DesktopAppRunning(d()),
+                "DESKTOP_APP_RUNNING",
+                "state",
+            ),
+            (
+                VpnError::TunnelOwnedElsewhere(d()),
+                "TUNNEL_OWNED_ELSEWHERE",
+                "state",
+            ),
+        ]
+    }
+
+    /// 新增变体时此处不再穷尽，编译失败以提醒补充上表
+    fn covered(err: &VpnError) {
+        match err {
+            VpnError::Config(_)
+            | VpnError::Connection(_)
+            | VpnError::AlreadyConnected
+            | VpnError::AlreadyConnecting
+            | VpnError::InvalidServer(_)
+            | VpnError::Network(_)
+            | VpnError::Io(_)
+            | VpnError::InvalidTransition(_)
+            | VpnError::KillSwitch(_)
+            | VpnError::Cancelled
+            | VpnError::PortInUse(_)
+            | VpnError::AuthRejected(_)
+            | VpnError::TlsFailure(_)
+            | VpnError::UdpBlocked(_)
+            | VpnError::PrivilegeDenied(_)
+            | VpnError::BinaryMissing(_)
+            | VpnError::CrashLoop(_)
+            | VpnError::DesktopAppRunning(_)
+            | VpnError::TunnelOwnedElsewhere(_) => {}
+        }
+    }
+
+    #[test]
+    fn codes_and_categories() {
+        let table = table();
+        for (err, code, category) in &table {
+            covered(err);
+            assert_eq!(err.code(), *code);
+            assert_eq!(err.category(), *category, "{}", code);
+        }
+        let codes: HashSet<_> = table.iter().map(|(_, code, _)| *code).collect();
+        assert_eq!(codes.len(), table.len());
+    }
+
+    #[test]
+    fn payload_mirrors_variant() {
+        for (err, code, category) in table() {
+            let payload = ErrorPayload::from(err.clone());
+            assert_eq!((payload.code, payload.category), (code, category));
+            assert_eq!(payload.message, err.user_message());
+            assert_eq!(payload.detail, err.detail());
+            // 文案与建议均为 "中文 / English"
+            assert!(payload.message.contains(" / "), "{}", code);
+            if let Some(action) = payload.action {
+                assert!(action.contains(" / "), "{}", code);
+            }
         }
+        assert_eq!(VpnError::Cancelled.detail(), None);
+        assert_eq!(
+            VpnError::Io("disk full".to_string()).detail().as_deref(),
+            Some("disk full")
+        );
     }
 }
@@ -1,20 +1,42 @@
+// 不带界面构建 (仅命令行客户端) 时共用模块中只供界面使用的部分不会被引用
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
+
+pub mod cli;
 mod constants;
 mod error;
+#[cfg(feature = "gui")]
 mod helper;
 mod logging;
+mod single_instance;
+#[cfg(feature = "gui")]
 mod tray;
 mod vpn;
 
+#[cfg(feature = "gui")]
 use tauri::Manager;
+#[cfg(feature = "gui")]
 use vpn::platform;
+#[cfg(feature = "gui")]
 use vpn::state::VpnState;
 
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     logging::init();
-    tracing::info!("Performing startup cleanup...");
-    platform::force_cleanup();
-    vpn::proxy::set_system_socks_proxy(false);
+    // 已有实例时只激活其主窗口，避免两个后端互相清理对方的隧道
+    let instance = match single_instance::acquire() {
+        single_instance::Acquire::Primary(guard) => guard,
+        single_instance::Acquire::Secondary => return,
+    };
+    // 界面崩溃重启时 sing-box 可能仍在运行，健康则接管而非清理；命令行建立的隧道同样保留
+    let previous_session = vpn::session::inspect();
+    if cli::owns_tunnel() {
+        tracing::info!("Tunnel started by tovpn-cli is running, skipping startup cleanup");
+    } else if !matches!(previous_session, vpn::session::StartupSession::Reattach(_)) {
+        tracing::info!("Performing startup cleanup...");
+        platform::force_cleanup();
+        vpn::proxy::set_system_socks_proxy(false);
+    }
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_http::init()) // 必须添加这一行
@@ -24,6 +46,7 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             // 状态检查
             vpn::state::check_vpn_status,
+            vpn::state::get_status_transitions,
             // Helper 管理
             helper::status::check_helper_status,
             helper::manager::install_helper,
@@ -32,7 +55,32 @@ pub fn run() {
             vpn::connect::precheck_tun_permission,
             vpn::connect::connect_hysteria,
             vpn::connect::disconnect_vpn,
+            vpn::connect::cancel_connect,
             vpn::connect::restart_vpn_monitor,
+            vpn::switch::switch_server,
+            // 自动重连
+            vpn::reconnect::get_reconnect_policy,
+            vpn::reconnect::set_reconnect_policy,
+            vpn::reconnect::set_reconnect_enabled,
+            vpn::autoconnect::get_autoconnect_policy,
+            vpn::autoconnect::set_autoconnect_policy,
+            vpn::autoconnect::get_network_identity,
+            vpn::timing::get_last_connect_timing,
+            vpn::history::get_session_history,
+            vpn::history::get_session_summary,
+            vpn::history::clear_session_history,
+            vpn::teardown::get_last_cleanup_report,
+            vpn::crashloop::get_crash_loop_status,
+            vpn::crashloop::clear_failing_node,
+            // 断网保护
+            vpn::kill_switch::get_kill_switch_status,
+            // 连接档案
+            vpn::profile::list_profiles,
+            vpn::profile::create_profile,
+            vpn::profile::update_profile,
+            vpn::profile::delete_profile,
+            vpn::profile::activate_profile,
+            vpn::profile::connect_profile,
             // Ping 功能
             vpn::ping::ping_nodes,
             vpn::ping::ping_single_node,
@@ -47,7 +95,14 @@ pub fn run() {
             tray::show_main_window,
             tray::minimize_to_tray,
         ])
-        .setup(|app| {
+        .setup(move |app| {
+            vpn::session::restore(app.handle(), previous_session, &app.state::<VpnState>());
+            vpn::autoconnect::start(app.handle().clone());
+            vpn::netwatch::start(app.handle().clone());
+            vpn::control::start(app.handle().clone());
+            if let Some(instance) = instance {
+                instance.listen(app.handle().clone());
+            }
             // 初始化托盘
             let _ = tray::create_tray(app.handle());
             Ok(())
@@ -84,11 +139,22 @@ pub fn run() {
         });
 }
 
+#[cfg(feature = "gui")]
 fn cleanup_on_exit(app_handle: &tauri::AppHandle) {
-    platform::force_cleanup();
+    if !cli::owns_tunnel() {
+        platform::force_cleanup();
+    }
     vpn::proxy::set_system_socks_proxy(false);
 
+    vpn::control::remove_socket(app_handle);
+
     if let Some(state) = app_handle.try_state::<VpnState>() {
+        vpn::history::finish(app_handle, &state, "app exit");
+        // 退出应用视同用户断开
+        if let Err(e) = vpn::kill_switch::disengage(&state) {
+            tracing::error!("Failed to remove kill switch on exit: {}", e);
+        }
         state.reset();
+        vpn::session::clear();
     }
 }
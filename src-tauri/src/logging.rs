@@ -34,3 +34,13 @@ pub fn init() {
 
     tracing::info!("Logging initialized");
 }
+
+/// 命令行客户端的日志：写入标准错误，标准输出只留给 JSON 结果
+pub fn init_stderr() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr).compact())
+        .init();
+}
@@ -0,0 +1,241 @@
+//! 单实例模块
+//! 两个实例会争用 1080/9090/9091 端口与缓存目录下的 pid 文件，且各自启动时的 force_cleanup
+//! 会清掉对方的隧道。按用户加文件锁保证只有一个后端运行；后启动的实例把激活请求转发给
+//! 已运行的实例 (显示其主窗口) 后直接退出
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "gui")]
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use crate::constants;
+use crate::vpn::security;
+
+/// 连接已运行实例的重试次数与间隔 (其监听可能尚未就绪)
+const ACTIVATE_RETRIES: u32 = 10;
+const ACTIVATE_RETRY_MS: u64 = 100;
+const ACTIVATE_MESSAGE: &str = "activate";
+/// 接收激活请求的读取时限；连接后不发送的客户端不能阻塞后续激活
+const ACTIVATE_READ_TIMEOUT_MS: u64 = 500;
+
+#[cfg(unix)]
+type Listener = std::os::unix::net::UnixListener;
+#[cfg(not(unix))]
+type Listener = std::net::TcpListener;
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+type Stream = std::net::TcpStream;
+
+/// 获取结果
+pub enum Acquire {
+    /// 当前为唯一实例；锁文件不可用时为 None (不阻止启动)
+    Primary(Option<InstanceGuard>),
+    /// 已有实例在运行，激活请求已转发
+    Secondary,
+}
+
+/// 持有期间保持实例锁；锁随进程退出自动释放
+pub struct InstanceGuard {
+    lock: File,
+    listener: Option<Listener>,
+}
+
+#[cfg(feature = "gui")]
+impl InstanceGuard {
+    /// setup 阶段调用：后台接收其他实例的激活请求
+    pub fn listen(self, app: AppHandle) {
+        let lock = self.lock;
+        let Some(listener) = self.listener else {
+            // 无法接收激活请求也要持有锁直到进程退出
+            std::mem::forget(lock);
+            return;
+        };
+        std::thread::spawn(move || {
+            // 锁文件随线程常驻，直到进程退出
+            let _lock = lock;
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                if is_activation(stream) {
+                    info!("Activation requested by another instance");
+                    let handle = app.clone();
+                    let _ = app.run_on_main_thread(move || crate::tray::show_main_window(handle));
+                }
+            }
+        });
+    }
+}
+
+/// 读取一条激活请求；超时、出错或内容不符时返回 false
+fn is_activation(stream: Stream) -> bool {
+    if stream
+        .set_read_timeout(Some(Duration::from_millis(ACTIVATE_READ_TIMEOUT_MS)))
+        .is_err()
+    {
+        return false;
+    }
+    let mut line = String::new();
+    // 限制长度，避免读入不换行的大量数据
+    let mut reader = BufReader::new(stream.take(64));
+    reader.read_line(&mut line).is_ok() && line.trim() == ACTIVATE_MESSAGE
+}
+
+/// 在启动清理之前调用
+pub fn acquire() -> Acquire {
+    let lock_path = constants::get_instance_lock_file();
+    if let Some(dir) = lock_path.parent() {
+        if let Err(e) = security::ensure_private_dir(dir) {
+            warn!("Instance lock dir unavailable: {}", e);
+            return Acquire::Primary(None);
+        }
+    }
+
+    match try_lock(&lock_path) {
+        Ok(Some(lock)) => {
+            let listener = bind_listener()
+                .map_err(|e| warn!("Activation listener unavailable: {}", e))
+                .ok();
+            info!(path = %lock_path.display(), "Single-instance lock acquired");
+            Acquire::Primary(Some(InstanceGuard { lock, listener }))
+        }
+        Ok(None) => {
+            info!("Another instance is running, forwarding activation");
+            if let Err(e) = activate_running() {
+                warn!("Failed to activate the running instance: {}", e);
+            }
+            Acquire::Secondary
+        }
+        Err(e) => {
+            warn!("Single-instance lock unavailable: {}", e);
+            Acquire::Primary(None)
+        }
+    }
+}
+
+/// 桌面应用是否在运行；命令行客户端据此避免与其争用隧道
+pub fn is_running() -> bool {
+    matches!(try_lock(&constants::get_instance_lock_file()), Ok(None))
+}
+
+/// 获取排他锁；已被其他实例持有时返回 None
+#[cfg(unix)]
+fn try_lock(path: &Path) -> std::io::Result<Option<File>> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        let _ = file.set_len(0);
+        let _ = write!(&file, "{}", std::process::id());
+        return Ok(Some(file));
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(None)
+    } else {
+        Err(err)
+    }
+}
+
+/// 获取排他锁；已被其他实例持有时返回 None
+#[cfg(windows)]
+fn try_lock(path: &Path) -> std::io::Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    // ERROR_SHARING_VIOLATION
+    const SHARING_VIOLATION: i32 = 32;
+    match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .share_mode(0)
+        .open(path)
+    {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.raw_os_error() == Some(SHARING_VIOLATION) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 监听激活请求：unix 为仅当前用户可访问的套接字
+#[cfg(unix)]
+fn bind_listener() -> std::io::Result<Listener> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = constants::get_instance_socket();
+    // 持有锁说明旧套接字来自已退出的实例
+    let _ = fs::remove_file(&path);
+    let listener = Listener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// 监听激活请求：Windows 为回环端口，端口号写入通道文件
+#[cfg(not(unix))]
+fn bind_listener() -> std::io::Result<Listener> {
+    let listener = Listener::bind(("127.0.0.1", 0))?;
+    let port = listener.local_addr()?.port();
+    fs::write(constants::get_instance_socket(), port.to_string())?;
+    Ok(listener)
+}
+
+#[cfg(unix)]
+fn connect_running() -> std::io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(constants::get_instance_socket())
+}
+
+#[cfg(not(unix))]
+fn connect_running() -> std::io::Result<std::net::TcpStream> {
+    let port: u16 = fs::read_to_string(constants::get_instance_socket())?
+        .trim()
+        .parse()
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid port"))?;
+    std::net::TcpStream::connect(("127.0.0.1", port))
+}
+
+/// 请求已运行的实例显示主窗口
+fn activate_running() -> std::io::Result<()> {
+    let mut last_err = None;
+    for _ in 0..ACTIVATE_RETRIES {
+        match connect_running() {
+            Ok(mut stream) => return writeln!(stream, "{}", ACTIVATE_MESSAGE),
+            Err(e) => last_err = Some(e),
+        }
+        std::thread::sleep(Duration::from_millis(ACTIVATE_RETRY_MS));
+    }
+    Err(last_err.unwrap_or_else(|| std::io::Error::other("activation failed")))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn activation_message_is_recognized() {
+        let (mut client, server) = Stream::pair().unwrap();
+        client.write_all(b"activate\n").unwrap();
+        assert!(is_activation(server));
+
+        let (mut client, server) = Stream::pair().unwrap();
+        client.write_all(b"something else\n").unwrap();
+        assert!(!is_activation(server));
+    }
+
+    #[test]
+    fn silent_client_times_out() {
+        let (_client, server) = Stream::pair().unwrap();
+        let started = Instant::now();
+        assert!(!is_activation(server));
+        assert!(started.elapsed() < Duration::from_millis(ACTIVATE_READ_TIMEOUT_MS * 4));
+    }
+}
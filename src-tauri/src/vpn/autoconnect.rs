@@ -0,0 +1,352 @@
+//! 自动连接策略模块
+//! 应用启动时自动连接；按当前网络决定是否走隧道：受信任的 SSID / 网卡保持直连，其余网络自动连接。
+//! 仅在网络标识变化时动作 (由 netwatch 的系统网络事件驱动)，用户在同一网络下手动断开或连接不会被反复覆盖
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, info, warn};
+
+use crate::error::{CommandResult, ErrorPayload, Result, VpnError};
+
+use super::config::ConnectConfig;
+use super::connect::{connect_with_config, disconnect_vpn, run_blocking};
+use super::monitor::emit_log;
+use super::platform::{self, NetworkIdentity};
+use super::profile::ProfileStore;
+use super::state::{VpnState, VpnStatusEnum};
+
+const POLICY_FILE: &str = "autoconnect.json";
+
+/// 自动连接策略
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoConnectPolicy {
+    /// 应用启动后自动连接
+    pub on_startup: bool,
+    /// 接入非受信任网络时自动连接，回到受信任网络时断开
+    pub on_untrusted_network: bool,
+    pub trusted_ssids: Vec<String>,
+    pub trusted_interfaces: Vec<String>,
+}
+
+impl AutoConnectPolicy {
+    pub fn load(dir: &Path) -> Self {
+        fs::read_to_string(dir.join(POLICY_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
+        fs::write(dir.join(POLICY_FILE), json)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        let entries = self.trusted_ssids.iter().chain(&self.trusted_interfaces);
+        for entry in entries {
+            if entry.trim().is_empty() || entry.len() > 64 {
+                return Err(VpnError::Config(
+                    "Trusted network names must be 1-64 characters".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 命中的受信任网络描述；未命中返回 None
+    pub fn trusted_match(&self, network: &NetworkIdentity) -> Option<String> {
+        network
+            .ssids
+            .iter()
+            .find(|ssid| self.trusted_ssids.contains(ssid))
+            .map(|ssid| format!("trusted SSID '{}'", ssid))
+            .or_else(|| {
+                network
+                    .interfaces
+                    .iter()
+                    .find(|iface| self.trusted_interfaces.contains(iface))
+                    .map(|iface| format!("trusted interface '{}'", iface))
+            })
+    }
+}
+
+/// 网络变化后的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Decision {
+    Connect,
+    /// 附命中的受信任网络描述
+    Disconnect(String),
+}
+
+/// 按策略与当前状态决定动作；没有可用网卡时 (离线/休眠唤醒过程中) 不做判断
+fn decide(
+    policy: &AutoConnectPolicy,
+    network: &NetworkIdentity,
+    status: VpnStatusEnum,
+) -> Option<Decision> {
+    if !policy.on_untrusted_network || network.interfaces.is_empty() {
+        return None;
+    }
+    match policy.trusted_match(network) {
+        Some(trusted) => matches!(
+            status,
+            VpnStatusEnum::Connected | VpnStatusEnum::Reconnecting
+        )
+        .then_some(Decision::Disconnect(trusted)),
+        None => matches!(status, VpnStatusEnum::Disconnected | VpnStatusEnum::Failed)
+            .then_some(Decision::Connect),
+    }
+}
+
+/// `vpn-autoconnect` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoConnectEvent {
+    /// connect / disconnect / skipped
+    pub action: &'static str,
+    pub reason: String,
+}
+
+fn emit_autoconnect(app: &AppHandle, action: &'static str, reason: &str) {
+    let _ = app.emit(
+        "vpn-autoconnect",
+        AutoConnectEvent {
+            action,
+            reason: reason.to_string(),
+        },
+    );
+}
+
+fn policy_dir(app: &AppHandle) -> Option<std::path::PathBuf> {
+    app.path().app_data_dir().ok()
+}
+
+fn load_policy(app: &AppHandle) -> AutoConnectPolicy {
+    policy_dir(app)
+        .map(|dir| AutoConnectPolicy::load(&dir))
+        .unwrap_or_default()
+}
+
+/// 最近使用的连接目标：本次运行的最后一次连接优先，其次为激活档案 (节点取档案偏好或最近使用的节点)
+fn last_used_target(app: &AppHandle) -> Option<(i32, ConnectConfig)> {
+    if let Some(target) = app.state::<VpnState>().get_last_connect() {
+        return Some(target);
+    }
+    let store = ProfileStore::load(&policy_dir(app)?);
+    let profile = store.active_profile()?;
+    let node = profile.node.as_ref().or(store.last_node.as_ref());
+    match profile.to_connect_config(node) {
+        Ok(target) => Some(target),
+        Err(e) => {
+            warn!(profile = %profile.name, "Auto-connect target unavailable: {}", e);
+            None
+        }
+    }
+}
+
+async fn auto_connect(app: &AppHandle, reason: &str) {
+    let Some((server_id, config)) = last_used_target(app) else {
+        info!(reason = %reason, "Auto-connect skipped: no last used profile");
+        emit_autoconnect(app, "skipped", "no last used profile");
+        return;
+    };
+
+    let state = app.state::<VpnState>();
+    // 反复异常退出的节点在标记期内不自动连接
+    if super::crashloop::failing_node(&state, server_id).is_some() {
+        info!(reason = %reason, server_id, "Auto-connect skipped: node marked as failing");
+        emit_autoconnect(app, "skipped", "node marked as failing");
+        return;
+    }
+
+    info!(reason = %reason, server_id, "Auto-connecting");
+    emit_autoconnect(app, "connect", reason);
+    emit_log(app, "info", &format!("Auto-connecting ({})", reason));
+
+    if let Err(e) = connect_with_config(app, &state, server_id, config).await {
+        warn!("Auto-connect failed: {}", e.message);
+    }
+}
+
+async fn auto_disconnect(app: &AppHandle, reason: &str) {
+    info!(reason = %reason, "Auto-disconnecting");
+    emit_autoconnect(app, "disconnect", reason);
+    emit_log(app, "info", &format!("Auto-disconnecting ({})", reason));
+
+    if let Err(e) = disconnect_vpn(app.clone(), app.state()).await {
+        warn!("Auto-disconnect failed: {}", e.message);
+    }
+}
+
+/// 网络标识变化后按策略连接或断开；返回是否已处理，netwatch 据此不再对同一变化重连
+pub async fn apply_network(app: &AppHandle, network: &NetworkIdentity) -> bool {
+    // 每次重新读取，设置修改后无需重启
+    let policy = load_policy(app);
+    let status = app.state::<VpnState>().get_status();
+    match decide(&policy, network, status) {
+        Some(Decision::Connect) => auto_connect(app, "untrusted network").await,
+        Some(Decision::Disconnect(trusted)) => auto_disconnect(app, &trusted).await,
+        None => return false,
+    }
+    true
+}
+
+/// 以当前网络作为首次观察立即判断 (启动时、重新启用策略时)
+async fn apply_current_network(app: &AppHandle) {
+    if let Ok(Some(network)) = run_blocking(platform::current_network).await {
+        debug!(?network, "Applying auto-connect policy to current network");
+        apply_network(app, &network).await;
+    }
+}
+
+/// setup 阶段调用：处理启动时自动连接；之后的网络变化由 netwatch 转交 `apply_network`
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let policy = load_policy(&app);
+        // 接管了运行中的会话时状态已是 Connected
+        if policy.on_startup && app.state::<VpnState>().get_status() == VpnStatusEnum::Disconnected
+        {
+            auto_connect(&app, "app startup").await;
+        }
+        if policy.on_untrusted_network {
+            apply_current_network(&app).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_autoconnect_policy(app_handle: AppHandle) -> CommandResult<AutoConnectPolicy> {
+    let dir = policy_dir(&app_handle)
+        .ok_or_else(|| VpnError::Io("App data dir unavailable".to_string()))?;
+    Ok(AutoConnectPolicy::load(&dir))
+}
+
+#[tauri::command]
+pub fn set_autoconnect_policy(
+    app_handle: AppHandle,
+    policy: AutoConnectPolicy,
+) -> CommandResult<AutoConnectPolicy> {
+    let dir = policy_dir(&app_handle)
+        .ok_or_else(|| VpnError::Io("App data dir unavailable".to_string()))?;
+    policy.validate().map_err(ErrorPayload::from)?;
+    let enabling =
+        policy.on_untrusted_network && !AutoConnectPolicy::load(&dir).on_untrusted_network;
+    policy.save(&dir).map_err(ErrorPayload::from)?;
+    info!(
+        on_startup = policy.on_startup,
+        on_untrusted_network = policy.on_untrusted_network,
+        "Auto-connect policy updated"
+    );
+    if enabling {
+        let app = app_handle.clone();
+        tauri::async_runtime::spawn(async move { apply_current_network(&app).await });
+    }
+    Ok(policy)
+}
+
+/// 当前网络标识，供界面添加受信任网络
+#[tauri::command]
+pub async fn get_network_identity() -> CommandResult<Option<NetworkIdentity>> {
+    run_blocking(platform::current_network)
+        .await
+        .map_err(ErrorPayload::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> AutoConnectPolicy {
+        AutoConnectPolicy {
+            on_startup: false,
+            on_untrusted_network: true,
+            trusted_ssids: vec!["home".to_string(), "office:5G".to_string()],
+            trusted_interfaces: vec!["eth0".to_string()],
+        }
+    }
+
+    fn network(ssids: &[&str], interfaces: &[&str]) -> NetworkIdentity {
+        NetworkIdentity {
+            ssids: ssids.iter().map(|s| s.to_string()).collect(),
+            interfaces: interfaces.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn trusted_match_prefers_ssid_then_interface() {
+        let policy = policy();
+        assert_eq!(
+            policy.trusted_match(&network(&["cafe", "office:5G"], &["wlan0", "eth0"])),
+            Some("trusted SSID 'office:5G'".to_string())
+        );
+        assert_eq!(
+            policy.trusted_match(&network(&["cafe"], &["wlan0", "eth0"])),
+            Some("trusted interface 'eth0'".to_string())
+        );
+        assert_eq!(policy.trusted_match(&network(&["cafe"], &["wlan0"])), None);
+        // 精确匹配，不做大小写或前缀匹配
+        assert_eq!(
+            policy.trusted_match(&network(&["Home", "home2"], &["eth01"])),
+            None
+        );
+    }
+
+    #[test]
+    fn validate_rejects_blank_and_overlong_names() {
+        assert!(policy().validate().is_ok());
+        assert!(AutoConnectPolicy::default().validate().is_ok());
+
+        let mut blank = policy();
+        blank.trusted_ssids.push("  ".to_string());
+        assert!(matches!(blank.validate(), Err(VpnError::Config(_))));
+
+        let mut long = policy();
+        long.trusted_interfaces.push("x".repeat(65));
+        assert!(long.validate().is_err());
+        long.trusted_interfaces.pop();
+        long.trusted_interfaces.push("x".repeat(64));
+        assert!(long.validate().is_ok());
+    }
+
+    #[test]
+    fn decide_follows_policy_and_status() {
+        let policy = policy();
+        let untrusted = network(&["cafe"], &["wlan0"]);
+        let trusted = network(&["home"], &["wlan0"]);
+
+        assert_eq!(
+            decide(&policy, &untrusted, VpnStatusEnum::Disconnected),
+            Some(Decision::Connect)
+        );
+        assert_eq!(
+            decide(&policy, &untrusted, VpnStatusEnum::Failed),
+            Some(Decision::Connect)
+        );
+        assert_eq!(decide(&policy, &untrusted, VpnStatusEnum::Connected), None);
+        assert_eq!(
+            decide(&policy, &trusted, VpnStatusEnum::Reconnecting),
+            Some(Decision::Disconnect("trusted SSID 'home'".to_string()))
+        );
+        assert_eq!(decide(&policy, &trusted, VpnStatusEnum::Disconnected), None);
+        // 离线时不判断
+        assert_eq!(
+            decide(&policy, &network(&[], &[]), VpnStatusEnum::Disconnected),
+            None
+        );
+
+        let disabled = AutoConnectPolicy {
+            on_untrusted_network: false,
+            ..policy
+        };
+        assert_eq!(
+            decide(&disabled, &untrusted, VpnStatusEnum::Disconnected),
+            None
+        );
+    }
+}
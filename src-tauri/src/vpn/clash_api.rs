@@ -0,0 +1,77 @@
+//! sing-box Clash API 会话凭据
+//! 每次连接随机生成端口与 secret，避免本机其他进程读取连接或篡改路由
+
+use std::net::TcpListener;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::CLASH_API_SECRET_BYTES;
+use crate::error::{Result, VpnError};
+
+/// Clash API 访问凭据（端口 + Bearer secret）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClashApi {
+    pub port: u16,
+    pub secret: String,
+}
+
+impl ClashApi {
+    /// 为新会话生成凭据：由系统分配空闲端口，secret 取自 CSPRNG
+    pub fn generate() -> Result<Self> {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map(|addr| addr.port())
+            .map_err(|e| VpnError::Io(format!("No free port for Clash API: {}", e)))?;
+
+        let mut bytes = [0u8; CLASH_API_SECRET_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+
+        Ok(Self {
+            port,
+            secret: hex::encode(bytes),
+        })
+    }
+
+    /// sing-box `external_controller` 监听地址
+    pub fn controller(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    /// 拼接 API 完整 URL，`path` 需以 `/` 开头
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+}
+
+/// sing-box connections API 响应
+#[derive(Debug, Deserialize)]
+struct ConnectionsResponse {
+    #[serde(rename = "downloadTotal")]
+    download_total: u64,
+    #[serde(rename = "uploadTotal")]
+    upload_total: u64,
+}
+
+/// 从 sing-box API 获取真实流量统计
+pub(crate) fn fetch_traffic_from_api(
+    client: &Option<reqwest::blocking::Client>,
+    api: Option<&ClashApi>,
+) -> Option<(u64, u64)> {
+    let client = client.as_ref()?;
+    let api = api?;
+
+    let response = client
+        .get(api.url("/connections"))
+        .bearer_auth(&api.secret)
+        .send()
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+
+    let text = response.text().ok()?;
+    serde_json::from_str::<ConnectionsResponse>(&text)
+        .ok()
+        .map(|conn| (conn.download_total, conn.upload_total))
+}
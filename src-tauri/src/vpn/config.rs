@@ -1,10 +1,93 @@
 //! VPN 连接配置模块
 
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
 use crate::constants;
 use crate::error::{Result, VpnError};
 
+use super::upstream::UpstreamProxy;
+
+/// TUN 入站选项：协议栈与路由范围
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TunOptions {
+    /// 协议栈: system / gvisor / mixed
+    pub stack: String,
+    pub strict_route: bool,
+    /// 仅将这些网段路由进 TUN（为空则接管全部流量）
+    pub route_address: Vec<String>,
+    /// 不进入 TUN 的网段，如办公网 10.0.0.0/8、组播/mDNS
+    pub route_exclude_address: Vec<String>,
+}
+
+impl Default for TunOptions {
+    fn default() -> Self {
+        Self {
+            stack: "gvisor".to_string(),
+            strict_route: true,
+            route_address: Vec::new(),
+            route_exclude_address: Vec::new(),
+        }
+    }
+}
+
+/// 节点连接参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerNode {
+    pub server_id: i32,
+    pub domain: String,
+    pub port: u16,
+    pub password: String,
+}
+
+/// connect_hysteria 参数，界面与控制接口共用；拒绝未知字段，拼错的参数不会被忽略
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectOptions {
+    pub server_id: i32,
+    pub domain: String,
+    pub port: u16,
+    pub password: String,
+    pub mode: String,
+    /// 0 = auto
+    pub server_mtu: u16,
+    pub server_dns: String,
+    #[serde(default)]
+    pub tun_options: TunOptions,
+    /// 空 = 不使用，"auto" = 环境变量探测，其余为代理 URL
+    #[serde(default)]
+    pub upstream_proxy: Option<String>,
+    #[serde(default)]
+    pub kill_switch: bool,
+    /// 同一会话内可热切换的其他节点
+    #[serde(default)]
+    pub alternates: Vec<ServerNode>,
+}
+
+impl ConnectOptions {
+    /// 解析上游代理并构建连接配置，返回 (节点 ID, 配置)
+    pub fn into_config(self) -> Result<(i32, ConnectConfig)> {
+        let upstream = UpstreamProxy::from_option(self.upstream_proxy.as_deref(), &self.domain)?;
+        let config = ConnectConfig::new(
+            self.domain,
+            self.port,
+            self.password,
+            self.mode,
+            self.server_mtu,
+            self.server_dns,
+        )
+        .with_tun_options(self.tun_options)
+        .with_upstream_proxy(upstream)
+        .with_kill_switch(self.kill_switch)
+        .with_alternates(self.alternates);
+        Ok((self.server_id, config))
+    }
+}
+
 /// 连接配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectConfig {
     pub server_host: String,
     pub server_port: u16,
@@ -12,6 +95,16 @@ pub struct ConnectConfig {
     pub mode: String,
     pub mtu: u16,
     pub dns: String,
+    #[serde(default)]
+    pub tun: TunOptions,
+    /// 节点出站经由的上游 SOCKS5 代理
+    pub upstream: Option<UpstreamProxy>,
+    /// 隧道断开时阻断直连出站 (仅 TUN 模式生效)
+    #[serde(default)]
+    pub kill_switch: bool,
+    /// 同一会话内可热切换的其他节点
+    #[serde(default)]
+    pub alternates: Vec<ServerNode>,
 }
 
 impl ConnectConfig {
@@ -30,9 +123,54 @@ impl ConnectConfig {
             mode,
             mtu,
             dns,
+            tun: TunOptions::default(),
+            upstream: None,
+            kill_switch: false,
+            alternates: Vec::new(),
         }
     }
 
+    pub fn with_tun_options(mut self, tun: TunOptions) -> Self {
+        self.tun = tun;
+        self
+    }
+
+    pub fn with_upstream_proxy(mut self, upstream: Option<UpstreamProxy>) -> Self {
+        self.upstream = upstream;
+        self
+    }
+
+    pub fn with_kill_switch(mut self, enabled: bool) -> Self {
+        self.kill_switch = enabled;
+        self
+    }
+
+    pub fn with_alternates(mut self, alternates: Vec<ServerNode>) -> Self {
+        self.alternates = alternates;
+        self
+    }
+
+    /// 以备选节点 `server_id` 为主节点重排，原主节点 `current_id` 移入备选
+    pub fn promote(&self, current_id: i32, server_id: i32) -> Option<ConnectConfig> {
+        let index = self
+            .alternates
+            .iter()
+            .position(|n| n.server_id == server_id)?;
+
+        let mut config = self.clone();
+        let node = config.alternates.remove(index);
+        config.alternates.insert(
+            0,
+            ServerNode {
+                server_id: current_id,
+                domain: std::mem::replace(&mut config.server_host, node.domain),
+                port: std::mem::replace(&mut config.server_port, node.port),
+                password: std::mem::replace(&mut config.password, node.password),
+            },
+        );
+        Some(config)
+    }
+
     /// 验证配置参数
     pub fn validate(&self) -> Result<()> {
         // 验证服务器地址
@@ -91,6 +229,51 @@ impl ConnectConfig {
             ));
         }
 
+        // 验证 TUN 协议栈与路由网段
+        if !["system", "gvisor", "mixed"].contains(&self.tun.stack.as_str()) {
+            return Err(VpnError::Config(
+                "Invalid TUN stack, must be 'system', 'gvisor' or 'mixed'".to_string(),
+            ));
+        }
+        for cidr in self
+            .tun
+            .route_address
+            .iter()
+            .chain(self.tun.route_exclude_address.iter())
+        {
+            if !is_valid_cidr(cidr) {
+                return Err(VpnError::Config(format!("Invalid route CIDR: {}", cidr)));
+            }
+        }
+
+        if let Some(upstream) = &self.upstream {
+            upstream.validate()?;
+        }
+
+        for node in &self.alternates {
+            if node.domain.is_empty() || node.port == 0 || node.password.is_empty() {
+                return Err(VpnError::InvalidServer(format!(
+                    "Alternate server {} is incomplete",
+                    node.server_id
+                )));
+            }
+        }
+
         Ok(())
     }
 }
+
+/// 校验 `地址/前缀` 格式的网段
+fn is_valid_cidr(cidr: &str) -> bool {
+    let Some((addr, prefix)) = cidr.split_once('/') else {
+        return false;
+    };
+    let Ok(prefix) = prefix.parse::<u8>() else {
+        return false;
+    };
+    match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => prefix <= 32,
+        Ok(IpAddr::V6(_)) => prefix <= 128,
+        Err(_) => false,
+    }
+}
@@ -2,28 +2,39 @@
 //! 版本：v2025-12-22-Final
 
 use std::fs;
-use std::net::TcpStream;
 use std::sync::atomic::Ordering;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde_json::json;
 use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
 use tauri_plugin_shell::ShellExt;
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
-use crate::constants::{self, get_cache_dir};
-use crate::error::{Result, VpnError};
+use crate::constants;
+use crate::error::{CommandResult, ErrorPayload, Result, VpnError};
 
-use super::config::ConnectConfig;
+use super::clash_api::ClashApi;
+use super::config::{ConnectConfig, ConnectOptions};
+use super::connectivity::{is_port_free, verify_tunnel, wait_for_port_ready};
+use super::crashloop;
+use super::kill_switch;
 use super::monitor::{
-    emit_log, emit_status_change, start_monitor, start_process_watchdog, stop_monitor,
-    stop_watchdog,
+    begin_attempt, emit_connection_error, emit_log, start_monitor, start_process_watchdog,
+    stop_monitor, stop_watchdog, transition_status,
 };
+use super::mtu;
 use super::platform;
+use super::reconnect;
 use super::security;
+use super::session;
+use super::singbox::diagnose::{self, Diagnosis};
 use super::singbox::{is_fatal_error, parse_log_level};
 use super::state::{VpnState, VpnStatusEnum};
+use super::teardown::{self, CleanupReport};
+use super::timing::{ConnectPhase, ConnectTimer};
 
 #[derive(serde::Serialize)]
 pub struct TunPrecheckResult {
@@ -48,70 +59,165 @@ pub fn precheck_tun_permission() -> TunPrecheckResult {
 pub async fn connect_hysteria(
     app_handle: AppHandle,
     state: tauri::State<'_, VpnState>,
+    options: ConnectOptions,
+) -> CommandResult<String> {
+    let (server_id, config) = options.into_config().map_err(ErrorPayload::from)?;
+    connect_with_config(&app_handle, &state, server_id, config).await
+}
+
+/// connect_hysteria / connect_profile 共用的连接流程
+pub async fn connect_with_config(
+    app_handle: &AppHandle,
+    state: &VpnState,
     server_id: i32,
-    domain: String,
-    port: u16,
-    password: String,
-    mode: String,
-    server_mtu: u16,
-    server_dns: String,
-) -> std::result::Result<String, String> {
-    let current_status = state.get_status();
-    if current_status == VpnStatusEnum::Connected {
-        return Err(VpnError::AlreadyConnected.user_message());
+    config: ConnectConfig,
+) -> CommandResult<String> {
+    if let Err(e) = config.validate() {
+        return Err(e.into());
     }
-    if current_status == VpnStatusEnum::Connecting {
-        return Err(VpnError::AlreadyConnecting.user_message());
+
+    // 连接前的清理会结束命令行建立的隧道
+    if crate::cli::owns_tunnel() {
+        return Err(VpnError::TunnelOwnedElsewhere(
+            "a tunnel started by tovpn-cli is running; run `tovpn-cli disconnect` first"
+                .to_string(),
+        )
+        .into());
     }
 
+    // 迁移与令牌登记在状态锁内完成：并发的连接请求只有一个能进入 Connecting，
+    // 被拒绝的一方不会取消已在进行的尝试
+    let cancel = begin_attempt(
+        app_handle,
+        state,
+        VpnStatusEnum::Connecting,
+        "connect requested",
+    )
+    .map_err(|e| match state.get_status() {
+        VpnStatusEnum::Connected => VpnError::AlreadyConnected,
+        VpnStatusEnum::Connecting | VpnStatusEnum::Reconnecting => VpnError::AlreadyConnecting,
+        _ => e,
+    })
+    .map_err(ErrorPayload::from)?;
+
+    let mut timer = ConnectTimer::new(app_handle, &config.mode);
+    timer.phase(ConnectPhase::Cleanup);
     info!("Pre-connection cleanup...");
-    fast_cleanup_before_connect(&app_handle, &state);
+    cleanup_before_connect(state).await;
+    state.set_server_id(Some(server_id));
 
-    let config = ConnectConfig::new(domain, port, password, mode.clone(), server_mtu, server_dns);
-    if let Err(e) = config.validate() {
-        return Err(e.user_message());
-    }
+    let result = async {
+        // 先于 sing-box 启动安装，避免隧道建立前的流量泄漏
+        check_cancelled(&cancel)?;
+        timer.phase(ConnectPhase::KillSwitch);
+        // 不随取消中断：安装完成后由失败/取消分支统一撤销，避免遗留规则
+        let (app, cfg) = (app_handle.clone(), config.clone());
+        run_blocking(move || {
+            let state = app.state::<VpnState>();
+            if kill_switch::applies_to(&cfg) {
+                kill_switch::engage(&state, &cfg)
+            } else {
+                kill_switch::disengage(&state)
+            }
+        })
+        .await??;
 
-    state.set_user_disconnect(false);
-    state.set_status(VpnStatusEnum::Connecting);
-    emit_status_change(&app_handle, &state);
-    state.set_server_id(Some(server_id));
+        do_connect(app_handle, state, &config, &cancel, &mut timer).await?;
+        // 进程就绪的同时到达的取消
+        check_cancelled(&cancel)
+    }
+    .await;
 
-    match do_connect(&app_handle, &state, &config).await {
+    match result {
         Ok(_) => {
-            state.set_status(VpnStatusEnum::Connected);
-            state.set_connected_at(
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs(),
+            timer.finish(state, "connected", None);
+            finish_connect(app_handle, state, server_id, &config, "tunnel established")
+                .map_err(ErrorPayload::from)?;
+            emit_log(app_handle, "info", "VPN connected successfully");
+            Ok("Connected".to_string())
+        }
+        Err(VpnError::Cancelled) => {
+            info!("Connection attempt cancelled");
+            timer.finish(state, "cancelled", None);
+            cleanup_connection(app_handle, state).await;
+            let released = release_kill_switch(app_handle).await;
+            let _ = transition_status(
+                app_handle,
+                state,
+                VpnStatusEnum::Disconnecting,
+                "connect cancelled",
             );
-            state.set_current_mode(&mode);
-            emit_status_change(&app_handle, &state);
-            emit_log(&app_handle, "info", "VPN connected successfully");
-
-            start_monitor(app_handle.clone(), &state);
-            if mode == "tun" {
-                start_process_watchdog(app_handle.clone(), &state);
+            if let Err(e) = released {
+                let _ = transition_status(
+                    app_handle,
+                    state,
+                    VpnStatusEnum::Failed,
+                    &format!("teardown failed: {}", e),
+                );
+                return Err(e.into());
             }
-
-            Ok("Connected".to_string())
+            let _ = transition_status(
+                app_handle,
+                state,
+                VpnStatusEnum::Disconnected,
+                "connect cancelled",
+            );
+            emit_log(app_handle, "info", "Connection cancelled");
+            Err(VpnError::Cancelled.into())
         }
         Err(e) => {
             error!("Connection failed: {}", e);
-            fast_cleanup_connection(&app_handle, &state, false);
-            let msg = e.user_message();
-            emit_log(&app_handle, "error", &format!("Connection failed: {}", msg));
-            Err(msg)
+            timer.finish(state, "failed", Some(e.user_message()));
+            cleanup_connection(app_handle, state).await;
+            // 隧道从未建立，无需继续阻断；撤销失败已提示，随后进入 Failed
+            let _ = release_kill_switch(app_handle).await;
+            let _ = transition_status(app_handle, state, VpnStatusEnum::Failed, &e.to_string());
+            emit_log(
+                app_handle,
+                "error",
+                &format!("Connection failed: {}", e.user_message()),
+            );
+            Err(e.into())
         }
     }
 }
 
+/// 进程已就绪后的收尾：记录会话信息、迁移到 Connected 并启动监控
+pub(super) fn finish_connect(
+    app_handle: &AppHandle,
+    state: &VpnState,
+    server_id: i32,
+    config: &ConnectConfig,
+    reason: &str,
+) -> Result<()> {
+    state.set_connected_at(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    );
+    state.set_current_mode(&config.mode);
+    transition_status(app_handle, state, VpnStatusEnum::Connected, reason)?;
+    state.set_last_connect(server_id, config.clone());
+    state.set_session_nodes(
+        std::iter::once(server_id)
+            .chain(config.alternates.iter().map(|n| n.server_id))
+            .collect(),
+    );
+    session::save(state);
+
+    start_monitor(app_handle.clone(), state);
+    if config.mode == "tun" {
+        start_process_watchdog(app_handle.clone(), state);
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn restart_vpn_monitor(
     app_handle: AppHandle,
     state: tauri::State<'_, VpnState>,
-) -> std::result::Result<String, String> {
+) -> CommandResult<String> {
     if state.get_status() != VpnStatusEnum::Connected {
         return Ok("Not connected".into());
     }
@@ -132,35 +238,123 @@ pub fn restart_vpn_monitor(
 pub async fn disconnect_vpn(
     app_handle: AppHandle,
     state: tauri::State<'_, VpnState>,
-) -> std::result::Result<String, String> {
-    if state.get_status() == VpnStatusEnum::Disconnected {
-        return Ok("Already disconnected".into());
+) -> CommandResult<String> {
+    match state.get_status() {
+        VpnStatusEnum::Disconnected => {
+            // 上次撤销失败时允许重试
+            release_kill_switch(&app_handle)
+                .await
+                .map_err(ErrorPayload::from)?;
+            return Ok("Already disconnected".into());
+        }
+        VpnStatusEnum::Disconnecting => return Ok("Disconnecting".into()),
+        VpnStatusEnum::Connecting => {
+            // 由连接流程在下一个检查点退出并完成清理
+            state.cancel_connect_attempt();
+            return Ok("Cancelling".into());
+        }
+        VpnStatusEnum::Failed => {
+            // 失败状态下进程已退出，只需确认清理；规则仍未撤销时保持 Failed
+            cleanup_connection(&app_handle, &state).await;
+            release_kill_switch(&app_handle)
+                .await
+                .map_err(ErrorPayload::from)?;
+            transition_status(
+                &app_handle,
+                &state,
+                VpnStatusEnum::Disconnected,
+                "user dismissed failure",
+            )
+            .map_err(ErrorPayload::from)?;
+            return Ok("Disconnected".into());
+        }
+        _ => {}
     }
 
-    state.set_user_disconnect(true);
-    state.set_status(VpnStatusEnum::Disconnecting);
-    emit_status_change(&app_handle, &state);
-
-    fast_cleanup_connection(&app_handle, &state, true);
+    // 重连中：停止后台重试
+    state.cancel_connect_attempt();
+    transition_status(
+        &app_handle,
+        &state,
+        VpnStatusEnum::Disconnecting,
+        "user requested disconnect",
+    )
+    .map_err(ErrorPayload::from)?;
+
+    cleanup_connection(&app_handle, &state).await;
+    if let Err(e) = release_kill_switch(&app_handle).await {
+        // 网络仍被阻断，不能报告为已断开；再次断开时重试撤销
+        let _ = transition_status(
+            &app_handle,
+            &state,
+            VpnStatusEnum::Failed,
+            &format!("teardown failed: {}", e),
+        );
+        return Err(e.into());
+    }
 
-    emit_status_change(&app_handle, &state);
+    transition_status(
+        &app_handle,
+        &state,
+        VpnStatusEnum::Disconnected,
+        "user disconnected",
+    )
+    .map_err(ErrorPayload::from)?;
     emit_log(&app_handle, "info", "VPN disconnected");
     Ok("Disconnected".into())
 }
 
-fn fast_cleanup_before_connect(_app_handle: &AppHandle, state: &VpnState) {
+/// 取消进行中的连接或重连
+#[tauri::command]
+pub async fn cancel_connect(
+    app_handle: AppHandle,
+    state: tauri::State<'_, VpnState>,
+) -> CommandResult<String> {
+    match state.get_status() {
+        VpnStatusEnum::Connecting | VpnStatusEnum::Reconnecting => {
+            disconnect_vpn(app_handle, state).await
+        }
+        _ => Ok("Nothing to cancel".into()),
+    }
+}
+
+/// 用户断开时撤销 kill switch；失败时规则保留，需提示用户
+async fn release_kill_switch(app_handle: &AppHandle) -> Result<()> {
+    let app = app_handle.clone();
+    let result = run_blocking(move || kill_switch::disengage(&app.state::<VpnState>()))
+        .await
+        .and_then(|r| r);
+    if let Err(e) = &result {
+        error!("Failed to remove kill switch: {}", e);
+        emit_log(
+            app_handle,
+            "error",
+            &format!("Kill switch still active, network blocked: {}", e),
+        );
+    }
+    result
+}
+
+pub(super) async fn cleanup_before_connect(state: &VpnState) {
     info!("=== Cleanup before connect ===");
 
     stop_watchdog(state);
     stop_monitor(state);
-    
-    platform::set_system_socks_proxy(false);
-    platform::force_cleanup();
+
+    let result = run_blocking(|| {
+        platform::set_system_socks_proxy(false);
+        platform::force_cleanup();
+    })
+    .await;
+    if let Err(e) = result {
+        warn!("Pre-connect cleanup error: {}", e);
+    }
     state.reset();
+    session::clear();
 
-    std::thread::sleep(Duration::from_millis(500));
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    if !is_port_free(constants::DEFAULT_SOCKS_PORT) {
+    if !is_port_free(constants::DEFAULT_SOCKS_PORT).await {
         warn!(
             "Port {} still occupied after cleanup",
             constants::DEFAULT_SOCKS_PORT
@@ -170,93 +364,146 @@ fn fast_cleanup_before_connect(_app_handle: &AppHandle, state: &VpnState) {
     info!("=== Cleanup completed ===");
 }
 
-fn fast_cleanup_connection(app_handle: &AppHandle, state: &VpnState, is_user_action: bool) {
+/// 停止 sing-box 并清理会话数据，返回清理报告；状态迁移由调用方负责
+///
+/// 同步版本，供 watchdog 线程等非异步上下文使用；异步路径使用 [`cleanup_connection`]
+pub fn fast_cleanup_connection(app_handle: &AppHandle, state: &VpnState) -> CleanupReport {
+    info!("=== Cleaning up connection ===");
+
+    stop_watchdog(state);
+    stop_monitor(state);
+    let baseline = state.get_network_baseline();
+    let report = teardown::run(&state.get_current_mode(), baseline.as_ref());
+    kill_child(state.take_child());
+    finish_cleanup(app_handle, state, &report);
+    report
+}
+
+/// [`fast_cleanup_connection`] 的异步版本，外部命令在阻塞线程池执行
+pub async fn cleanup_connection(app_handle: &AppHandle, state: &VpnState) -> CleanupReport {
     info!("=== Cleaning up connection ===");
 
     stop_watchdog(state);
     stop_monitor(state);
-    
-    platform::set_system_socks_proxy(false);
 
     let mode = state.get_current_mode();
+    let child = state.take_child();
+    let baseline = state.get_network_baseline();
+    let report = run_blocking(move || {
+        let report = teardown::run(&mode, baseline.as_ref());
+        kill_child(child);
+        report
+    })
+    .await
+    .unwrap_or_else(|e| {
+        warn!("Cleanup error: {}", e);
+        CleanupReport::default()
+    });
+    finish_cleanup(app_handle, state, &report);
+    report
+}
 
-    if mode == "tun" {
-        info!("Stopping TUN mode...");
-        if let Err(e) = platform::stop_singbox_tun_as_root() {
-            warn!("TUN stop error: {}", e);
-            platform::force_cleanup();
-        }
-    } else {
-        info!("Stopping SOCKS mode...");
-        if let Some(child) = state.take_child() {
-            let _ = child.kill();
-        }
-        platform::force_cleanup();
+/// 结束 sidecar 子进程；登记进程已在清理中结束，这里是未登记成功时的兜底，已退出的进程忽略错误
+fn kill_child(child: Option<CommandChild>) {
+    if let Some(child) = child {
+        let _ = child.kill();
     }
+}
 
+fn finish_cleanup(app_handle: &AppHandle, state: &VpnState, report: &CleanupReport) {
+    teardown::emit_warning(app_handle, report);
+    state.set_cleanup_report(report.clone());
     state.reset();
-
-    if !is_user_action {
-        emit_status_change(app_handle, state);
-    }
+    session::clear();
 }
 
-fn is_port_free(port: u16) -> bool {
-    TcpStream::connect_timeout(
-        &format!("127.0.0.1:{}", port).parse().unwrap(),
-        Duration::from_millis(constants::PORT_CHECK_TIMEOUT_MS),
+async fn verify_socks_proxy_working(port: u16) -> bool {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let handshake = async {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        stream.write_all(&[0x05, 0x01, 0x00]).await?;
+        let mut response = [0u8; 2];
+        stream.read_exact(&mut response).await?;
+        Ok::<_, std::io::Error>(response == [0x05, 0x00])
+    };
+    matches!(
+        tokio::time::timeout(Duration::from_millis(1000), handshake).await,
+        Ok(Ok(true))
     )
-    .is_err()
 }
 
-fn wait_for_port_ready(port: u16, timeout_ms: u64) -> bool {
-    let start = Instant::now();
-    let timeout = Duration::from_millis(timeout_ms);
-
-    while start.elapsed() < timeout {
-        if TcpStream::connect_timeout(
-            &format!("127.0.0.1:{}", port).parse().unwrap(),
-            Duration::from_millis(100),
-        )
-        .is_ok()
-        {
-            info!("Port {} is ready", port);
-            return true;
+/// 取出通道中已缓冲的 sing-box 输出（日志任务启动前调用）
+fn drain_output(rx: &mut tauri::async_runtime::Receiver<CommandEvent>) -> String {
+    let mut output = String::new();
+    while let Ok(event) = rx.try_recv() {
+        if let CommandEvent::Stdout(bytes) | CommandEvent::Stderr(bytes) = event {
+            output.push_str(&String::from_utf8_lossy(&bytes));
+            output.push('\n');
         }
-        std::thread::sleep(Duration::from_millis(100));
     }
+    output
+}
 
-    warn!("Port {} not ready after {}ms", port, timeout_ms);
-    false
+fn check_cancelled(cancel: &CancellationToken) -> Result<()> {
+    if cancel.is_cancelled() {
+        Err(VpnError::Cancelled)
+    } else {
+        Ok(())
+    }
 }
 
-fn verify_socks_proxy_working(port: u16) -> bool {
-    use std::io::{Read, Write};
-
-    if let Ok(mut stream) = TcpStream::connect(format!("127.0.0.1:{}", port)) {
-        stream
-            .set_read_timeout(Some(Duration::from_millis(500)))
-            .ok();
-        stream
-            .set_write_timeout(Some(Duration::from_millis(500)))
-            .ok();
-
-        let handshake = [0x05, 0x01, 0x00];
-        if stream.write_all(&handshake).is_ok() {
-            let mut response = [0u8; 2];
-            if stream.read_exact(&mut response).is_ok() {
-                return response[0] == 0x05 && response[1] == 0x00;
-            }
-        }
+/// 启动失败时按诊断结果确定错误类型，并附带诊断发送 `vpn-connection-error`
+fn report_start_failure(
+    app_handle: &AppHandle,
+    diagnosis: Option<Diagnosis>,
+    fallback: VpnError,
+) -> VpnError {
+    let error = diagnosis
+        .as_ref()
+        .map(Diagnosis::to_error)
+        .unwrap_or(fallback);
+    if let Some(d) = &diagnosis {
+        warn!(kind = ?d.kind, evidence = %d.evidence, "sing-box start failure diagnosed");
+    }
+    emit_connection_error(app_handle, &error, diagnosis.as_ref(), true);
+    error
+}
+
+/// 在阻塞线程池执行外部命令、DNS 解析等阻塞操作，避免占用异步运行时
+pub(super) async fn run_blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| VpnError::Io(e.to_string()))
+}
+
+/// 同 [`run_blocking`]；取消时立即返回，后台线程结束后结果被丢弃
+async fn run_cancellable<T, F>(cancel: &CancellationToken, f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::select! {
+        res = run_blocking(f) => res,
+        _ = cancel.cancelled() => Err(VpnError::Cancelled),
     }
-    false
 }
 
-async fn do_connect(
+/// 启动 sing-box；每个耗时步骤前后检查 `cancel`，取消时回收已启动的进程
+pub(super) async fn do_connect(
     app_handle: &AppHandle,
     state: &VpnState,
     config: &ConnectConfig,
+    cancel: &CancellationToken,
+    timer: &mut ConnectTimer,
 ) -> Result<()> {
+    // 断开时据此确认路由、DNS 与系统代理已复原
+    state.set_network_baseline(Some(run_blocking(platform::network_snapshot).await?));
+
     let app_dir = app_handle
         .path()
         .app_data_dir()
@@ -265,43 +512,57 @@ async fn do_connect(
         fs::create_dir_all(&app_dir)?;
     }
 
-    let config_filename = if config.mode == "tun" {
-        "config-tun.json"
-    } else {
-        "config-socks.json"
-    };
-    let mut config_path = app_dir.join(config_filename);
-
-    let cache_filename = if config.mode == "tun" {
-        "cache-tun.db"
-    } else {
-        "cache-socks.db"
-    };
-    let cache_path = app_dir.join(cache_filename);
-
-    let config_content = super::singbox::generate_config(config, &cache_path)?;
-
-    let config_json = serde_json::to_string_pretty(&config_content)
-        .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
-
-    if fs::write(&config_path, &config_json).is_err() {
-        let ts = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        config_path = app_dir.join(format!("config-{}-{}.json", config.mode, ts));
-        fs::write(&config_path, &config_json)?;
+    // MTU = 0 (auto) 时探测路径 MTU 并写回本次连接的配置
+    let mut config = config.clone();
+    if config.mode == "tun" && config.mtu == 0 {
+        timer.phase(ConnectPhase::ProbingMtu);
+        let (dir, host, port) = (
+            app_dir.clone(),
+            config.server_host.clone(),
+            config.server_port,
+        );
+        let probe = run_cancellable(cancel, move || mtu::resolve_tun_mtu(&dir, &host, port));
+        match probe.await? {
+            Some(measurement) => {
+                config.mtu = measurement.tun_mtu;
+                let _ = app_handle.emit("vpn-mtu", &measurement);
+                state.set_mtu(Some(measurement));
+            }
+            None => warn!(
+                "Path MTU probe failed, falling back to {}",
+                constants::MTU_MAX
+            ),
+        }
+    }
+    let config = &config;
+
+    // 预先解析节点域名：慢 DNS 单独计时，结果进入系统缓存供生成配置时复用
+    if config.server_host.parse::<std::net::IpAddr>().is_err() {
+        timer.phase(ConnectPhase::ResolvingServer);
+        let (host, port) = (config.server_host.clone(), config.server_port);
+        let ips =
+            run_cancellable(cancel, move || super::singbox::resolve_ipv4(&host, port)).await?;
+        if ips.is_empty() {
+            warn!(
+                "Server {} did not resolve, leaving it to sing-box",
+                config.server_host
+            );
+        }
     }
 
-    let signature = security::sign_content(&config_json);
-    let sig_path = config_path.with_extension("json.sig");
-    fs::write(&sig_path, signature)?;
-    security::set_secure_permissions(&config_path);
-    security::set_secure_permissions(&sig_path);
+    timer.phase(ConnectPhase::GeneratingConfig);
+    let clash_api = ClashApi::generate()?;
+    state.set_clash_api(Some(clash_api.clone()));
 
-    if !security::verify_config(&config_path, &sig_path) {
-        return Err(VpnError::Config("Security check failed".into()));
-    }
+    let (cfg, dir, api) = (config.clone(), app_dir.clone(), clash_api.clone());
+    let config_path = run_cancellable(cancel, move || {
+        super::singbox::write_config(&dir, &cfg, &api)
+    })
+    .await??;
+    check_cancelled(cancel)?;
+
+    timer.phase(ConnectPhase::VerifyingSignature);
+    security::sign_config(&config_path)?;
 
     let config_path_str = config_path
         .to_str()
@@ -312,16 +573,63 @@ async fn do_connect(
     if config.mode == "tun" {
         info!("Starting TUN mode...");
 
-        platform::set_system_socks_proxy(false);
+        run_blocking(|| platform::set_system_socks_proxy(false)).await?;
         state.set_current_mode("tun");
 
-        let log_path = get_cache_dir()
-            .join("tovpn-tun.log")
-            .to_string_lossy()
-            .to_string();
+        let log_path = diagnose::tun_log_path().to_string_lossy().to_string();
 
-        platform::run_singbox_tun_as_root(&config_path_str, &log_path)
-            .map_err(VpnError::Connection)?;
+        // 提权提示期间被取消：等提示返回后再停止，避免遗留 root 进程
+        check_cancelled(cancel)?;
+        if !run_blocking(platform::check_singbox_installed).await? {
+            return Err(VpnError::BinaryMissing(
+                "sing-box not found in system path".to_string(),
+            ));
+        }
+        timer.phase(ConnectPhase::RequestingPrivileges);
+        let mut start = tauri::async_runtime::spawn_blocking(move || {
+            platform::run_singbox_tun_as_root(&config_path_str, &log_path)
+        });
+        tokio::select! {
+            res = &mut start => {
+                if let Err(msg) = res.map_err(|e| VpnError::Io(e.to_string()))? {
+                    // 提权失败信息优先，其次是 sing-box 写入日志的启动错误
+                    let diagnosis = match diagnose::classify(&msg) {
+                        Some(d) => Some(d),
+                        None => run_blocking(diagnose::diagnose_tun_log).await?,
+                    };
+                    return Err(report_start_failure(
+                        app_handle,
+                        diagnosis,
+                        VpnError::Connection(msg),
+                    ));
+                }
+            }
+            _ = cancel.cancelled() => {
+                tauri::async_runtime::spawn(async move {
+                    if let Ok(Ok(())) = start.await {
+                        let _ = run_blocking(platform::stop_singbox_tun_as_root).await;
+                    }
+                });
+                return Err(VpnError::Cancelled);
+            }
+        }
+
+        timer.phase(ConnectPhase::VerifyingTunnel);
+        if let Err(e) = verify_tunnel(&clash_api, cancel).await {
+            let VpnError::Network(msg) = e else {
+                return Err(e);
+            };
+            // sing-box 日志中的出站错误比延迟测试的结果更具体
+            let diagnosis = match run_blocking(diagnose::diagnose_tun_log).await? {
+                Some(d) => Some(d),
+                None => diagnose::classify(&msg),
+            };
+            return Err(report_start_failure(
+                app_handle,
+                diagnosis,
+                VpnError::Network(format!("Tunnel verification failed: {}", msg)),
+            ));
+        }
 
         return Ok(());
     }
@@ -329,73 +637,157 @@ async fn do_connect(
     // === SOCKS 模式 ===
     info!("Starting SOCKS mode...");
     state.set_current_mode("socks");
+    check_cancelled(cancel)?;
+    timer.phase(ConnectPhase::Spawning);
+
+    // 端口被其他程序占用时，就绪检测会误判为 sing-box 已启动
+    if !is_port_free(constants::DEFAULT_SOCKS_PORT).await {
+        return Err(VpnError::PortInUse(format!(
+            "127.0.0.1:{} is held by another process",
+            constants::DEFAULT_SOCKS_PORT
+        )));
+    }
 
     let sidecar = app_handle
         .shell()
         .sidecar("sing-box")
-        .map_err(|e| VpnError::Config(format!("Sidecar error: {}", e)))?;
+        .map_err(|e| VpnError::BinaryMissing(format!("Sidecar error: {}", e)))?;
 
     let (mut rx, child) = sidecar
         .args(["run", "-c", &config_path_str])
         .spawn()
-        .map_err(|e| VpnError::Connection(format!("Spawn error: {}", e)))?;
+        .map_err(|e| VpnError::BinaryMissing(format!("Spawn error: {}", e)))?;
 
+    platform::owned::register(child.pid(), false);
     state.set_child(child);
+    let session = state.begin_session();
 
+    timer.phase(ConnectPhase::WaitingForInbound);
     info!("Waiting for SOCKS port...");
-    if !wait_for_port_ready(constants::DEFAULT_SOCKS_PORT, 8000) {
+    let ready = wait_for_port_ready(constants::DEFAULT_SOCKS_PORT, 8000, cancel).await;
+    if cancel.is_cancelled() {
+        if let Some(child) = state.take_child() {
+            let _ = child.kill();
+        }
+        return Err(VpnError::Cancelled);
+    }
+    if !ready {
         error!("SOCKS port not ready");
         if let Some(child) = state.take_child() {
             let _ = child.kill();
         }
-        return Err(VpnError::Connection("SOCKS port not ready".to_string()));
+        // 日志任务尚未启动，启动阶段的输出仍在通道中
+        let output = drain_output(&mut rx);
+        return Err(report_start_failure(
+            app_handle,
+            diagnose::classify(&output),
+            VpnError::Connection("SOCKS port not ready".to_string()),
+        ));
     }
 
+    timer.phase(ConnectPhase::VerifyingTunnel);
     info!("Verifying SOCKS proxy...");
-    std::thread::sleep(Duration::from_millis(200));
-    if !verify_socks_proxy_working(constants::DEFAULT_SOCKS_PORT) {
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    if !verify_socks_proxy_working(constants::DEFAULT_SOCKS_PORT).await {
         warn!("SOCKS proxy verification failed, but process seems running");
     }
+    if let Err(e) = verify_tunnel(&clash_api, cancel).await {
+        let VpnError::Network(msg) = e else {
+            return Err(e);
+        };
+        let output = drain_output(&mut rx);
+        let diagnosis = diagnose::classify(&output).or_else(|| diagnose::classify(&msg));
+        return Err(report_start_failure(
+            app_handle,
+            diagnosis,
+            VpnError::Network(format!("Tunnel verification failed: {}", msg)),
+        ));
+    }
 
-    platform::set_system_socks_proxy(true);
+    run_blocking(|| platform::set_system_socks_proxy(true)).await?;
 
-    let user_disconnect = state.get_user_disconnect_flag();
     let app = app_handle.clone();
     tauri::async_runtime::spawn(async move {
         let mut has_fatal = false;
         let mut fatal_msg = String::new();
+        let mut diagnosis: Option<Diagnosis> = None;
 
         while let Some(event) = rx.recv().await {
             match event {
                 CommandEvent::Stdout(bytes) | CommandEvent::Stderr(bytes) => {
                     let line = String::from_utf8_lossy(&bytes);
                     let trimmed = line.trim();
-                    if trimmed.is_empty() { continue; }
+                    if trimmed.is_empty() {
+                        continue;
+                    }
                     let (level, msg) = parse_log_level(trimmed);
                     if !msg.is_empty() && level != "debug" {
                         emit_log(&app, level, &msg);
                     }
+                    if let Some(found) = diagnose::classify_line(trimmed) {
+                        // 同优先级取最新一条
+                        let replace = match &diagnosis {
+                            Some(current) => found.kind <= current.kind,
+                            None => true,
+                        };
+                        if replace {
+                            diagnosis = Some(found);
+                        }
+                    }
                     if is_fatal_error(trimmed) {
                         has_fatal = true;
                         fatal_msg = msg;
                     }
                 }
                 CommandEvent::Terminated(payload) => {
-                    if !user_disconnect.load(Ordering::SeqCst) {
-                        if has_fatal {
+                    // 只有当前会话在 Connected 状态下退出才算异常；
+                    // 用户断开或已被新会话替换的进程退出直接忽略
+                    let vpn_state = app.state::<VpnState>();
+                    let unexpected = vpn_state.current_session() == session
+                        && vpn_state.get_status() == VpnStatusEnum::Connected;
+                    if unexpected {
+                        let reason = if has_fatal {
+                            format!("sing-box fatal error: {}", fatal_msg)
+                        } else {
+                            format!("sing-box exited (code {:?})", payload.code)
+                        };
+                        // 清理前记录，节点与模式随后会被重置
+                        let error = diagnosis
+                            .as_ref()
+                            .map(Diagnosis::to_error)
+                            .unwrap_or_else(|| VpnError::Connection(reason.clone()));
+                        let crash_loop = crashloop::record_exit(
+                            &app,
+                            &vpn_state,
+                            vpn_state.get_server_id().unwrap_or_default(),
+                            &vpn_state.get_current_mode(),
+                            &error,
+                            &reason,
+                        );
+                        if !crash_loop && (has_fatal || diagnosis.is_some()) {
+                            emit_connection_error(&app, &error, diagnosis.as_ref(), true);
+                        }
+                        cleanup_connection(&app, &vpn_state).await;
+                        if crash_loop || !reconnect::handle_unexpected_exit(&app, &reason) {
+                            let reason = if crash_loop {
+                                format!("crash loop: {}", reason)
+                            } else {
+                                reason
+                            };
+                            let _ =
+                                transition_status(&app, &vpn_state, VpnStatusEnum::Failed, &reason);
                             let _ = app.emit(
-                                "vpn-connection-error",
-                                json!({ "error": fatal_msg, "fatal": true }),
+                                "vpn-process-terminated",
+                                json!({
+                                    "reason": match (crash_loop, has_fatal) {
+                                        (true, _) => "crash_loop",
+                                        (false, true) => "fatal",
+                                        (false, false) => "exit",
+                                    },
+                                    "exit_code": payload.code
+                                }),
                             );
                         }
-                        fast_cleanup_connection(&app, app.state::<VpnState>().inner(), false);
-                        let _ = app.emit(
-                            "vpn-process-terminated",
-                            json!({
-                                "reason": if has_fatal { "fatal" } else { "exit" },
-                                "exit_code": payload.code
-                            }),
-                        );
                     }
                     break;
                 }
@@ -405,4 +797,4 @@ async fn do_connect(
     });
 
     Ok(())
-}
\ No newline at end of file
+}
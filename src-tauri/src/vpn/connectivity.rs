@@ -2,9 +2,15 @@
 
 use serde::Serialize;
 use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
-use crate::constants::DEFAULT_SOCKS_PORT;
+use crate::constants::{self, DEFAULT_SOCKS_PORT};
+use crate::error::{Result, VpnError};
+
+use super::clash_api::ClashApi;
 
 /// 连通性测试结果
 #[derive(Debug, Clone, Serialize)]
@@ -19,7 +25,7 @@ pub struct ConnectivityResult {
 ///
 /// TUN 模式：直接测试（流量自动通过 TUN 接口）
 /// SOCKS 模式：通过 SOCKS 代理测试
-#[tauri::command]
+#[cfg_attr(feature = "gui", tauri::command)]
 pub async fn test_connectivity(use_proxy: bool) -> ConnectivityResult {
     let test_url = "http://www.gstatic.com/generate_204";
 
@@ -103,7 +109,7 @@ pub async fn test_connectivity(use_proxy: bool) -> ConnectivityResult {
 }
 
 /// 测试 DNS 解析
-#[tauri::command]
+#[cfg_attr(feature = "gui", tauri::command)]
 pub async fn test_dns_resolution(domain: String) -> ConnectivityResult {
     info!(domain = %domain, "Testing DNS resolution");
 
@@ -142,4 +148,110 @@ pub async fn test_dns_resolution(domain: String) -> ConnectivityResult {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+pub(crate) async fn is_port_free(port: u16) -> bool {
+    let probe = TcpStream::connect(("127.0.0.1", port));
+    !matches!(
+        tokio::time::timeout(
+            Duration::from_millis(constants::PORT_CHECK_TIMEOUT_MS),
+            probe
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+pub(crate) async fn wait_for_port_ready(
+    port: u16,
+    timeout_ms: u64,
+    cancel: &CancellationToken,
+) -> bool {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+    while Instant::now() < deadline && !cancel.is_cancelled() {
+        let probe = TcpStream::connect(("127.0.0.1", port));
+        if let Ok(Ok(_)) = tokio::time::timeout(Duration::from_millis(100), probe).await {
+            info!("Port {} is ready", port);
+            return true;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            _ = cancel.cancelled() => break,
+        }
+    }
+
+    warn!("Port {} not ready after {}ms", port, timeout_ms);
+    false
+}
+
+/// Clash API 延迟测试的响应
+#[derive(serde::Deserialize)]
+struct DelayResponse {
+    delay: Option<u32>,
+    message: Option<String>,
+}
+
+/// 经 Clash API 延迟测试发起一次穿过节点出站的真实请求，确认节点可达且接受认证
+///
+/// 成功返回延迟 (毫秒)；节点不可达或拒绝时返回 `VpnError::Network`，附 sing-box 给出的原因
+pub(crate) async fn verify_tunnel(api: &ClashApi, cancel: &CancellationToken) -> Result<u32> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(
+            constants::TUNNEL_VERIFY_TIMEOUT_MS + 1000,
+        ))
+        .build()?;
+    let url = api.url(&format!(
+        "/proxies/proxy/delay?timeout={}&url={}",
+        constants::TUNNEL_VERIFY_TIMEOUT_MS,
+        constants::TUNNEL_TEST_URL
+    ));
+
+    // Clash API 可能晚于入站就绪；首次 QUIC 握手偶有失败，延迟测试失败时重试一次
+    let api_deadline = Instant::now() + Duration::from_secs(3);
+    let mut attempts = 0;
+    loop {
+        let request = client.get(&url).bearer_auth(&api.secret).send();
+        let response = tokio::select! {
+            r = request => r,
+            _ = cancel.cancelled() => return Err(VpnError::Cancelled),
+        };
+
+        let message = match response {
+            Ok(response) => {
+                let ok = response.status().is_success();
+                let body: DelayResponse = response.json().await.unwrap_or(DelayResponse {
+                    delay: None,
+                    message: None,
+                });
+                match (ok, body.delay) {
+                    (true, Some(delay)) => {
+                        info!(delay, "Tunnel verified");
+                        return Ok(delay);
+                    }
+                    _ => body
+                        .message
+                        .unwrap_or_else(|| "delay test failed".to_string()),
+                }
+            }
+            Err(e) if e.is_connect() && Instant::now() < api_deadline => {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(200)) => {}
+                    _ = cancel.cancelled() => return Err(VpnError::Cancelled),
+                }
+                continue;
+            }
+            Err(e) => format!("Clash API unavailable: {}", e),
+        };
+
+        attempts += 1;
+        if attempts >= 2 {
+            warn!("Tunnel verification failed: {}", message);
+            return Err(VpnError::Network(message));
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {}
+            _ = cancel.cancelled() => return Err(VpnError::Cancelled),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! 本地控制接口模块
+//! 在应用数据目录下监听 Unix 域套接字 (control.sock)，以换行分隔的 JSON-RPC 2.0 提供
+//! connect / connect_profile / disconnect / status / switch_server / subscribe，直接调用界面使用的同一组 Tauri 命令。
+//! 认证依赖文件权限：套接字仅当前用户可读写，并额外校验对端 uid
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{CommandResult, VpnError};
+
+use super::config::{ConnectOptions, ServerNode};
+use super::connect::{connect_hysteria, disconnect_vpn};
+use super::profile::connect_profile;
+use super::state::check_vpn_status;
+use super::switch::switch_server;
+
+/// 可订阅的事件，与发给前端的事件同名同负载
+const EVENTS: &[&str] = &[
+    "vpn-status-change",
+    "vpn-state-transition",
+    "vpn-connect-phase",
+    "vpn-connection-error",
+    "vpn-traffic",
+    "vpn-latency",
+    "vpn-mtu",
+    "vpn-log",
+    "vpn-reconnect",
+    "vpn-server-switched",
+    "vpn-network-change",
+    "vpn-autoconnect",
+    "vpn-cleanup-warning",
+    "vpn-process-crashed",
+    "vpn-process-terminated",
+    "vpn-crash-loop",
+];
+
+// JSON-RPC 2.0 错误码
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// 命令返回的业务错误，`data` 为与前端相同的 ErrorPayload
+const COMMAND_ERROR: i32 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    /// 缺省时为通知，不回复
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+/// connect_profile 参数，与 connect_profile 相同；全部省略时连接当前档案
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ProfileParams {
+    name: Option<String>,
+    node: Option<ServerNode>,
+    alternates: Option<Vec<ServerNode>>,
+}
+
+#[derive(Deserialize)]
+struct SwitchParams {
+    server_id: i32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SubscribeParams {
+    /// 为空时订阅全部事件
+    events: Vec<String>,
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    // 省略 params 等同空对象
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn command_result<T: Serialize>(result: CommandResult<T>) -> RpcResult {
+    match result {
+        Ok(value) => Ok(json!(value)),
+        Err(payload) => Err(RpcError {
+            code: COMMAND_ERROR,
+            message: payload.message.clone(),
+            data: Some(json!(payload)),
+        }),
+    }
+}
+
+fn response(id: Value, result: RpcResult) -> String {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+    .to_string()
+}
+
+/// 调用对应的 Tauri 命令
+async fn dispatch(app: &AppHandle, method: &str, params: Value) -> RpcResult {
+    match method {
+        "connect" => {
+            let options: ConnectOptions = parse_params(params)?;
+            command_result(connect_hysteria(app.clone(), app.state(), options).await)
+        }
+        "connect_profile" => {
+            let ProfileParams {
+                name,
+                node,
+                alternates,
+            } = parse_params(params)?;
+            command_result(connect_profile(app.clone(), app.state(), name, node, alternates).await)
+        }
+        "disconnect" => command_result(disconnect_vpn(app.clone(), app.state()).await),
+        "status" => command_result(
+            check_vpn_status(app.state())
+                .await
+                .map_err(|e| VpnError::Connection(e).payload()),
+        ),
+        "switch_server" => {
+            let SwitchParams { server_id } = parse_params(params)?;
+            command_result(switch_server(app.clone(), app.state(), server_id).await)
+        }
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", other),
+        )),
+    }
+}
+
+/// setup 阶段调用：启动控制套接字；不支持 Unix 域套接字的平台不启动
+pub fn start(app: AppHandle) {
+    #[cfg(unix)]
+    server::start(app);
+    #[cfg(not(unix))]
+    {
+        let _ = app;
+        tracing::info!("Control socket unavailable on this platform");
+    }
+}
+
+/// 退出时移除套接字文件
+pub fn remove_socket(app: &AppHandle) {
+    if let Ok(dir) = app.path().app_data_dir() {
+        let _ = std::fs::remove_file(dir.join(crate::constants::control::SOCKET_FILE));
+    }
+}
+
+#[cfg(unix)]
+mod server {
+    use std::path::Path;
+
+    use tauri::async_runtime::{self, JoinHandle};
+    use tauri::{AppHandle, Listener, Manager};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::broadcast::{self, error::RecvError};
+    use tokio::sync::mpsc::{self, error::TrySendError};
+    use tokio_util::sync::CancellationToken;
+    use tracing::{debug, info, warn};
+
+    use crate::constants::control;
+
+    use super::*;
+
+    /// (事件名, JSON 负载)
+    type EventMessage = (&'static str, String);
+
+    fn bind(path: &Path) -> std::io::Result<UnixListener> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // 单实例保证旧套接字来自已退出的进程
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    pub(super) fn start(app: AppHandle) {
+        let path = match app.path().app_data_dir() {
+            Ok(dir) => dir.join(control::SOCKET_FILE),
+            Err(e) => {
+                warn!("Control socket unavailable: {}", e);
+                return;
+            }
+        };
+
+        // 转发发给前端的事件；没有订阅者时 send 失败，忽略
+        let (events, _) = broadcast::channel::<EventMessage>(control::EVENT_BUFFER);
+        for &name in EVENTS {
+            let events = events.clone();
+            app.listen_any(name, move |event| {
+                let _ = events.send((name, event.payload().to_string()));
+            });
+        }
+
+        async_runtime::spawn(async move {
+            let listener = match bind(&path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!(path = %path.display(), "Control socket bind failed: {}", e);
+                    return;
+                }
+            };
+            info!(path = %path.display(), "Control socket listening");
+
+            let uid = unsafe { libc::getuid() };
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Control socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+                // 套接字权限之外再确认对端是同一用户
+                match stream.peer_cred() {
+                    Ok(cred) if cred.uid() == uid => {}
+                    _ => {
+                        warn!("Rejected control connection from another user");
+                        continue;
+                    }
+                }
+                async_runtime::spawn(serve(app.clone(), stream, events.clone()));
+            }
+        });
+    }
+
+    /// 订阅事件；重复订阅时替换之前的事件集合。写通道积压满时客户端已停止读取，取消 `lagging` 断开它
+    fn subscribe(
+        params: Value,
+        events: &broadcast::Sender<EventMessage>,
+        out: &mpsc::Sender<String>,
+        lagging: &CancellationToken,
+        subscription: &mut Option<JoinHandle<()>>,
+    ) -> RpcResult {
+        let SubscribeParams { events: wanted } = parse_params(params)?;
+        if let Some(unknown) = wanted.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Unknown event '{}'", unknown),
+            ));
+        }
+        let wanted: Vec<String> = if wanted.is_empty() {
+            EVENTS.iter().map(|e| e.to_string()).collect()
+        } else {
+            wanted
+        };
+
+        if let Some(previous) = subscription.take() {
+            previous.abort();
+        }
+        let mut rx = events.subscribe();
+        let (out, lagging, filter) = (out.clone(), lagging.clone(), wanted.clone());
+        *subscription = Some(async_runtime::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok((event, payload)) => {
+                        if !filter.iter().any(|e| e == event) {
+                            continue;
+                        }
+                        let payload: Value = serde_json::from_str(&payload).unwrap_or(Value::Null);
+                        let notification = json!({
+                            "jsonrpc": "2.0",
+                            "method": "event",
+                            "params": { "event": event, "payload": payload },
+                        });
+                        match out.try_send(notification.to_string()) {
+                            Ok(()) => {}
+                            Err(TrySendError::Full(_)) => {
+                                warn!("Control subscriber not reading, disconnecting");
+                                lagging.cancel();
+                                break;
+                            }
+                            Err(TrySendError::Closed(_)) => break,
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(skipped, "Control subscriber lagging, events dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }));
+        Ok(json!({ "events": wanted }))
+    }
+
+    /// 排队写出一条消息；客户端已断开或因积压被断开时返回 false
+    async fn send(out: &mpsc::Sender<String>, lagging: &CancellationToken, line: String) -> bool {
+        tokio::select! {
+            sent = out.send(line) => sent.is_ok(),
+            _ = lagging.cancelled() => false,
+        }
+    }
+
+    /// 处理一个客户端：请求按顺序执行，事件通知经同一写通道穿插发送
+    async fn serve(app: AppHandle, stream: UnixStream, events: broadcast::Sender<EventMessage>) {
+        debug!("Control client connected");
+        let (reader, mut writer) = stream.into_split();
+        let (out, mut out_rx) = mpsc::channel::<String>(control::CLIENT_QUEUE);
+        let lagging = CancellationToken::new();
+        let writer_task = async_runtime::spawn(async move {
+            while let Some(mut line) = out_rx.recv().await {
+                line.push('\n');
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut subscription = None;
+        let mut lines = BufReader::new(reader).lines();
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => line,
+                _ = lagging.cancelled() => break,
+            };
+            let Ok(Some(line)) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request: Request = match serde_json::from_str(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    let error = RpcError::new(PARSE_ERROR, e.to_string());
+                    if !send(&out, &lagging, response(Value::Null, Err(error))).await {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            debug!(method = %request.method, "Control request");
+            let result = if request.method == "subscribe" {
+                subscribe(request.params, &events, &out, &lagging, &mut subscription)
+            } else {
+                dispatch(&app, &request.method, request.params).await
+            };
+            if let Some(id) = request.id {
+                if !send(&out, &lagging, response(id, result)).await {
+                    break;
+                }
+            }
+        }
+
+        if let Some(task) = subscription {
+            task.abort();
+        }
+        // 被断开的客户端可能仍卡在写出上
+        if lagging.is_cancelled() {
+            writer_task.abort();
+        }
+        debug!("Control client disconnected");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorPayload;
+
+    fn connect_params() -> Value {
+        json!({
+            "server_id": 7,
+            "domain": "node.example.com",
+            "port": 443,
+            "password": "secret",
+            "mode": "tun",
+            "server_mtu": 0,
+            "server_dns": "google",
+        })
+    }
+
+    #[test]
+    fn parse_params_treats_null_as_empty_object() {
+        let params: SubscribeParams = parse_params(Value::Null).unwrap();
+        assert!(params.events.is_empty());
+        let params: ProfileParams = parse_params(Value::Null).unwrap();
+        assert!(params.name.is_none() && params.node.is_none() && params.alternates.is_none());
+
+        // 必填字段缺失或类型不符为 INVALID_PARAMS
+        let error = parse_params::<SwitchParams>(Value::Null).err().unwrap();
+        assert_eq!(error.code, INVALID_PARAMS);
+        let error = parse_params::<SwitchParams>(json!({ "server_id": "7" }))
+            .err()
+            .unwrap();
+        assert_eq!(error.code, INVALID_PARAMS);
+        let params: SwitchParams = parse_params(json!({ "server_id": 7 })).unwrap();
+        assert_eq!(params.server_id, 7);
+    }
+
+    #[test]
+    fn connect_params_reject_unknown_fields() {
+        let options: ConnectOptions = parse_params(connect_params()).unwrap();
+        assert_eq!(options.server_id, 7);
+        assert!(!options.kill_switch && options.alternates.is_empty());
+
+        let mut params = connect_params();
+        params["killswitch"] = json!(true);
+        let error = parse_params::<ConnectOptions>(params).err().unwrap();
+        assert_eq!(error.code, INVALID_PARAMS);
+        assert!(error.message.contains("killswitch"), "{}", error.message);
+
+        let error = parse_params::<ProfileParams>(json!({ "profile": "home" }))
+            .err()
+            .unwrap();
+        assert_eq!(error.code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn command_errors_map_to_rpc_errors() {
+        assert_eq!(
+            command_result::<&str>(Ok("Connected")).unwrap(),
+            json!("Connected")
+        );
+
+        let payload: ErrorPayload = VpnError::InvalidServer("server 9".to_string()).payload();
+        let error = command_result::<()>(Err(payload.clone())).unwrap_err();
+        assert_eq!(error.code, COMMAND_ERROR);
+        assert_eq!(error.message, payload.message);
+        let data = error.data.unwrap();
+        assert_eq!(data["code"], json!(payload.code));
+        assert_eq!(data["category"], json!(payload.category));
+    }
+
+    #[test]
+    fn responses_follow_json_rpc() {
+        let ok: Value = serde_json::from_str(&response(json!(1), Ok(json!("done")))).unwrap();
+        assert_eq!(ok, json!({ "jsonrpc": "2.0", "id": 1, "result": "done" }));
+
+        let error = RpcError::new(METHOD_NOT_FOUND, "Unknown method 'x'");
+        let err: Value = serde_json::from_str(&response(Value::Null, Err(error))).unwrap();
+        assert_eq!(
+            err,
+            json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": METHOD_NOT_FOUND, "message": "Unknown method 'x'" },
+            })
+        );
+    }
+
+    #[test]
+    fn events_are_unique_and_include_mtu() {
+        assert!(EVENTS.contains(&"vpn-mtu"));
+        let mut names = EVENTS.to_vec();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), EVENTS.len());
+    }
+}
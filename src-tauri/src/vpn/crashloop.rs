@@ -0,0 +1,319 @@
+//! 崩溃循环保护模块
+//! 记录 sing-box 的意外退出与自动重连中的启动失败及其归类原因。同一节点在统计窗口内退出达到阈值后
+//! 不再自动重启：节点被标记为故障一段时间，只发出一条可操作的错误；标记期间自动连接与重连跳过该节点，
+//! 用户仍可手动连接
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+use crate::constants::crash_loop;
+use crate::error::VpnError;
+
+use super::monitor::emit_connection_error;
+use super::state::VpnState;
+
+/// 一次退出
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitRecord {
+    /// 退出时间 (秒)
+    pub at: u64,
+    pub server_id: i32,
+    pub mode: String,
+    /// 归类原因，取错误码 (如 AUTH_REJECTED / TLS_FAILURE / CONNECTION_FAILED)
+    pub cause: &'static str,
+    pub detail: String,
+}
+
+/// 被标记为故障的节点（同时作为 `vpn-crash-loop` 事件负载）
+#[derive(Debug, Clone, Serialize)]
+pub struct FailingNode {
+    pub server_id: i32,
+    /// 标记起止时间 (秒)
+    pub since: u64,
+    pub until: u64,
+    /// 窗口内的退出次数与最后一次的原因
+    pub exits: u32,
+    pub cause: &'static str,
+    pub detail: String,
+}
+
+/// 查询结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashLoopStatus {
+    pub recent_exits: Vec<ExitRecord>,
+    pub failing_nodes: Vec<FailingNode>,
+}
+
+/// 退出记录与故障节点（保存在 `VpnState`，`reset` 不清除）
+#[derive(Debug, Default)]
+pub struct CrashTracker {
+    exits: VecDeque<ExitRecord>,
+    failing: HashMap<i32, FailingNode>,
+}
+
+impl CrashTracker {
+    /// 记录一次退出；窗口内同一节点达到阈值时标记该节点并返回标记
+    fn record(&mut self, record: ExitRecord) -> Option<FailingNode> {
+        let now = record.at;
+        let window_start = now.saturating_sub(crash_loop::WINDOW_SECS);
+        let (server_id, cause, detail) = (record.server_id, record.cause, record.detail.clone());
+
+        self.exits.push_back(record);
+        while self.exits.len() > crash_loop::HISTORY_LIMIT {
+            self.exits.pop_front();
+        }
+
+        let exits = self
+            .exits
+            .iter()
+            .filter(|e| e.server_id == server_id && e.at >= window_start)
+            .count();
+        if exits < crash_loop::THRESHOLD {
+            return None;
+        }
+
+        let node = FailingNode {
+            server_id,
+            since: now,
+            until: now + crash_loop::FAILING_SECS,
+            exits: exits as u32,
+            cause,
+            detail,
+        };
+        self.failing.insert(server_id, node.clone());
+        Some(node)
+    }
+
+    fn failing(&mut self, server_id: i32, now: u64) -> Option<FailingNode> {
+        self.prune(now);
+        self.failing.get(&server_id).cloned()
+    }
+
+    /// 移除已过标记期的节点
+    fn prune(&mut self, now: u64) {
+        self.failing.retain(|_, node| node.until > now);
+    }
+
+    /// 清除节点的标记与退出记录；`server_id` 缺省时全部清除
+    fn clear(&mut self, server_id: Option<i32>) {
+        match server_id {
+            Some(id) => {
+                self.failing.remove(&id);
+                self.exits.retain(|e| e.server_id != id);
+            }
+            None => *self = Self::default(),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// 是否归咎于节点或配置；取消、授权、端口占用等与节点无关的失败不计入
+fn counts(error: &VpnError) -> bool {
+    matches!(
+        error,
+        VpnError::Config(_)
+            | VpnError::Connection(_)
+            | VpnError::InvalidServer(_)
+            | VpnError::Network(_)
+            | VpnError::AuthRejected(_)
+            | VpnError::TlsFailure(_)
+            | VpnError::UdpBlocked(_)
+    )
+}
+
+/// 记录 sing-box 退出或自动重连中的启动失败
+///
+/// 判定为崩溃循环时标记节点、发出 `vpn-crash-loop` 与一条 `CrashLoop` 连接错误并返回 true，
+/// 调用方应停止自动重连并进入 Failed
+pub fn record_exit(
+    app: &AppHandle,
+    state: &VpnState,
+    server_id: i32,
+    mode: &str,
+    error: &VpnError,
+    detail: &str,
+) -> bool {
+    if !counts(error) {
+        return false;
+    }
+    let record = ExitRecord {
+        at: now_secs(),
+        server_id,
+        mode: mode.to_string(),
+        cause: error.code(),
+        detail: detail.to_string(),
+    };
+    info!(server_id, cause = record.cause, "sing-box exit recorded");
+
+    let Some(node) = state
+        .crash_tracker
+        .lock()
+        .ok()
+        .and_then(|mut tracker| tracker.record(record))
+    else {
+        return false;
+    };
+
+    warn!(
+        server_id,
+        exits = node.exits,
+        cause = node.cause,
+        "Crash loop detected, node marked as failing"
+    );
+    emit_connection_error(app, &crash_loop_error(&node), None, true);
+    let _ = app.emit("vpn-crash-loop", &node);
+    true
+}
+
+/// 节点当前是否被标记为故障
+pub fn failing_node(state: &VpnState, server_id: i32) -> Option<FailingNode> {
+    state
+        .crash_tracker
+        .lock()
+        .ok()
+        .and_then(|mut tracker| tracker.failing(server_id, now_secs()))
+}
+
+pub fn crash_loop_error(node: &FailingNode) -> VpnError {
+    VpnError::CrashLoop(format!(
+        "server {} exited {} times within {}s (last cause {}: {})",
+        node.server_id,
+        node.exits,
+        crash_loop::WINDOW_SECS,
+        node.cause,
+        node.detail
+    ))
+}
+
+/// 最近的退出记录 (新的在前) 与仍在标记期内的故障节点
+#[tauri::command]
+pub fn get_crash_loop_status(state: tauri::State<'_, VpnState>) -> CrashLoopStatus {
+    let now = now_secs();
+    let Ok(mut tracker) = state.crash_tracker.lock() else {
+        return CrashLoopStatus {
+            recent_exits: Vec::new(),
+            failing_nodes: Vec::new(),
+        };
+    };
+    tracker.prune(now);
+    CrashLoopStatus {
+        recent_exits: tracker.exits.iter().rev().cloned().collect(),
+        failing_nodes: tracker.failing.values().cloned().collect(),
+    }
+}
+
+/// 清除节点的故障标记与退出记录；`server_id` 缺省时全部清除
+#[tauri::command]
+pub fn clear_failing_node(state: tauri::State<'_, VpnState>, server_id: Option<i32>) {
+    if let Ok(mut tracker) = state.crash_tracker.lock() {
+        tracker.clear(server_id);
+    }
+    info!(?server_id, "Failing node mark cleared");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit(tracker: &mut CrashTracker, server_id: i32, at: u64) -> Option<FailingNode> {
+        tracker.record(ExitRecord {
+            at,
+            server_id,
+            mode: "tun".to_string(),
+            cause: "CONNECTION_FAILED",
+            detail: format!("exit at {}", at),
+        })
+    }
+
+    #[test]
+    fn threshold_inside_window_marks_node() {
+        let mut tracker = CrashTracker::default();
+        assert!(exit(&mut tracker, 1, 1000).is_none());
+        assert!(exit(&mut tracker, 1, 1060).is_none());
+        // 第一次退出恰好在窗口边界上，仍计入
+        let node = exit(&mut tracker, 1, 1000 + crash_loop::WINDOW_SECS).unwrap();
+        assert_eq!(node.server_id, 1);
+        assert_eq!(node.exits as usize, crash_loop::THRESHOLD);
+        assert_eq!(node.until, node.since + crash_loop::FAILING_SECS);
+        assert_eq!(node.detail, "exit at 1120");
+        assert!(tracker.failing(1, 1121).is_some());
+    }
+
+    #[test]
+    fn exits_outside_window_do_not_count() {
+        let mut tracker = CrashTracker::default();
+        assert!(exit(&mut tracker, 1, 1000).is_none());
+        assert!(exit(&mut tracker, 1, 1100).is_none());
+        assert!(exit(&mut tracker, 1, 1000 + crash_loop::WINDOW_SECS + 1).is_none());
+        assert!(tracker.failing(1, 1200).is_none());
+    }
+
+    #[test]
+    fn exits_are_counted_per_node() {
+        let mut tracker = CrashTracker::default();
+        assert!(exit(&mut tracker, 1, 1000).is_none());
+        assert!(exit(&mut tracker, 2, 1001).is_none());
+        assert!(exit(&mut tracker, 1, 1002).is_none());
+        assert!(exit(&mut tracker, 2, 1003).is_none());
+        assert!(exit(&mut tracker, 1, 1004).is_some());
+        assert!(tracker.failing(2, 1005).is_none());
+    }
+
+    #[test]
+    fn mark_expires() {
+        let mut tracker = CrashTracker::default();
+        for at in [1000, 1001, 1002] {
+            exit(&mut tracker, 1, at);
+        }
+        let until = 1002 + crash_loop::FAILING_SECS;
+        assert!(tracker.failing(1, until - 1).is_some());
+        assert!(tracker.failing(1, until).is_none());
+        assert!(tracker.failing.is_empty());
+    }
+
+    #[test]
+    fn clear_removes_mark_and_history() {
+        let mut tracker = CrashTracker::default();
+        for at in [1000, 1001, 1002] {
+            exit(&mut tracker, 1, at);
+            exit(&mut tracker, 2, at);
+        }
+        tracker.clear(Some(1));
+        assert!(tracker.failing(1, 1003).is_none());
+        assert!(tracker.failing(2, 1003).is_some());
+        // 清除后需重新累计到阈值
+        assert!(exit(&mut tracker, 1, 1004).is_none());
+
+        tracker.clear(None);
+        assert!(tracker.failing(2, 1005).is_none());
+        assert!(tracker.exits.is_empty());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut tracker = CrashTracker::default();
+        for i in 0..(crash_loop::HISTORY_LIMIT as u64 * 2) {
+            exit(&mut tracker, i as i32, 1000 + i);
+        }
+        assert_eq!(tracker.exits.len(), crash_loop::HISTORY_LIMIT);
+    }
+
+    #[test]
+    fn only_node_failures_count() {
+        assert!(counts(&VpnError::TlsFailure(String::new())));
+        assert!(counts(&VpnError::Connection(String::new())));
+        assert!(!counts(&VpnError::Cancelled));
+        assert!(!counts(&VpnError::PrivilegeDenied(String::new())));
+        assert!(!counts(&VpnError::PortInUse(String::new())));
+    }
+}
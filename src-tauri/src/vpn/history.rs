@@ -0,0 +1,376 @@
+//! 会话历史模块
+//! 每个 Connected 会话结束时向应用数据目录的 sessions.jsonl 追加一条记录
+//! (节点、模式、起止时间、流量、峰值速度、平均延迟与结束原因)，提供查询、按天/节点汇总与清空命令
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::error::{CommandResult, ErrorPayload, Result, VpnError};
+
+use super::state::{StatusTransition, VpnState, VpnStatusEnum};
+
+const HISTORY_FILE: &str = "sessions.jsonl";
+
+/// 进行中会话的累计统计；进入 Connected 时开始，离开时写入历史
+#[derive(Debug, Clone, Default)]
+pub struct ActiveSession {
+    pub server_id: i32,
+    pub mode: String,
+    pub started_at: u64,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    pub peak_upload_speed: u64,
+    pub peak_download_speed: u64,
+    pub latency_sum_ms: u64,
+    pub latency_samples: u32,
+    /// 会话开始时 Clash API 已报告的累计流量；热切换不重启 sing-box，新会话从切换时起算
+    pub base_up: u64,
+    pub base_down: u64,
+}
+
+/// 历史记录条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    pub server_id: i32,
+    pub mode: String,
+    /// 开始/结束时间 (秒)
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub duration_secs: u64,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    /// 峰值速度 (字节/秒)
+    pub peak_upload_speed: u64,
+    pub peak_download_speed: u64,
+    pub avg_latency_ms: Option<u32>,
+    /// 结束原因，取自离开 Connected 时的状态迁移原因
+    pub reason: String,
+}
+
+/// 按天或按节点的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    /// 日期 (YYYY-MM-DD) 或节点 ID
+    pub key: String,
+    pub sessions: u32,
+    pub total_secs: u64,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    pub avg_latency_ms: Option<u32>,
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(HISTORY_FILE))
+        .map_err(|e| VpnError::Io(e.to_string()))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn append(app: &AppHandle, entry: &SessionEntry) -> Result<()> {
+    let path = history_path(app)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let line = serde_json::to_string(entry)
+        .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// 读取全部记录；损坏的行（如写入中途崩溃）跳过
+fn load(app: &AppHandle) -> Result<Vec<SessionEntry>> {
+    let content = match fs::read_to_string(history_path(app)?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// 结束进行中的会话并写入历史；没有进行中的会话时忽略
+pub fn finish(app: &AppHandle, state: &VpnState, reason: &str) {
+    if let Some(active) = state.take_active_session() {
+        record(app, active, reason);
+    }
+}
+
+/// 节点热切换：结束旧节点的会话，并以当前节点开始新会话
+pub fn restart(app: &AppHandle, state: &VpnState, reason: &str) {
+    let Some(previous) = state.take_active_session() else {
+        return;
+    };
+    let next = ActiveSession {
+        server_id: state.get_server_id().unwrap_or_default(),
+        mode: previous.mode.clone(),
+        started_at: now_secs(),
+        base_up: previous.base_up + previous.bytes_up,
+        base_down: previous.base_down + previous.bytes_down,
+        ..Default::default()
+    };
+    record(app, previous, reason);
+    state.begin_active_session(next);
+}
+
+fn record(app: &AppHandle, active: ActiveSession, reason: &str) {
+    let ended_at = now_secs();
+    let entry = SessionEntry {
+        server_id: active.server_id,
+        mode: active.mode,
+        started_at: active.started_at,
+        ended_at,
+        duration_secs: ended_at.saturating_sub(active.started_at),
+        bytes_up: active.bytes_up,
+        bytes_down: active.bytes_down,
+        peak_upload_speed: active.peak_upload_speed,
+        peak_download_speed: active.peak_download_speed,
+        avg_latency_ms: (active.latency_samples > 0)
+            .then(|| (active.latency_sum_ms / active.latency_samples as u64) as u32),
+        reason: reason.to_string(),
+    };
+    info!(
+        server_id = entry.server_id,
+        duration_secs = entry.duration_secs,
+        reason = %entry.reason,
+        "Session ended"
+    );
+    if let Err(e) = append(app, &entry) {
+        warn!("Failed to record session history: {}", e);
+    }
+}
+
+/// 状态迁移钩子：进入 Connected 开始统计，离开 Connected 写入历史
+pub fn on_transition(app: &AppHandle, state: &VpnState, record: &StatusTransition) {
+    if record.from == VpnStatusEnum::Connected {
+        finish(app, state, &record.reason);
+    }
+    if record.to == VpnStatusEnum::Connected {
+        state.begin_active_session(ActiveSession {
+            server_id: state.get_server_id().unwrap_or_default(),
+            mode: state.get_current_mode(),
+            started_at: state.get_connected_at(),
+            ..Default::default()
+        });
+    }
+}
+
+/// 公历日期 (days since 1970-01-01 → YYYY-MM-DD)
+fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 会话所属日期：按本地时区下的开始时间计，跨午夜的会话整体计入开始那天
+fn day_of(entry: &SessionEntry, utc_offset_secs: i64) -> String {
+    civil_date((entry.started_at as i64 + utc_offset_secs).div_euclid(86_400))
+}
+
+fn summarize(
+    entries: &[SessionEntry],
+    key: impl Fn(&SessionEntry) -> String,
+) -> Vec<SessionSummary> {
+    let mut groups: BTreeMap<String, (SessionSummary, u64, u32)> = BTreeMap::new();
+    for entry in entries {
+        let k = key(entry);
+        let (summary, latency_sum, latency_count) = groups.entry(k.clone()).or_insert((
+            SessionSummary {
+                key: k,
+                sessions: 0,
+                total_secs: 0,
+                bytes_up: 0,
+                bytes_down: 0,
+                avg_latency_ms: None,
+            },
+            0,
+            0,
+        ));
+        summary.sessions += 1;
+        summary.total_secs += entry.duration_secs;
+        summary.bytes_up += entry.bytes_up;
+        summary.bytes_down += entry.bytes_down;
+        if let Some(latency) = entry.avg_latency_ms {
+            *latency_sum += latency as u64;
+            *latency_count += 1;
+        }
+    }
+    groups
+        .into_values()
+        .map(|(mut summary, latency_sum, latency_count)| {
+            summary.avg_latency_ms =
+                (latency_count > 0).then(|| (latency_sum / latency_count as u64) as u32);
+            summary
+        })
+        .collect()
+}
+
+/// 最近的会话记录，新的在前；可按节点过滤
+#[tauri::command]
+pub fn get_session_history(
+    app_handle: AppHandle,
+    limit: Option<usize>,
+    server_id: Option<i32>,
+) -> CommandResult<Vec<SessionEntry>> {
+    let entries = load(&app_handle).map_err(ErrorPayload::from)?;
+    Ok(entries
+        .into_iter()
+        .rev()
+        .filter(|e| server_id.is_none_or(|id| e.server_id == id))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+/// 按天 (`group_by = "day"`，`utc_offset_secs` 为本地时区偏移) 或按节点 (`"server"`) 汇总
+#[tauri::command]
+pub fn get_session_summary(
+    app_handle: AppHandle,
+    group_by: String,
+    utc_offset_secs: Option<i64>,
+) -> CommandResult<Vec<SessionSummary>> {
+    let entries = load(&app_handle).map_err(ErrorPayload::from)?;
+    match group_by.as_str() {
+        "day" => {
+            let offset = utc_offset_secs.unwrap_or(0);
+            Ok(summarize(&entries, |e| day_of(e, offset)))
+        }
+        "server" => Ok(summarize(&entries, |e| e.server_id.to_string())),
+        other => Err(VpnError::Config(format!("Unknown grouping '{}'", other)).into()),
+    }
+}
+
+#[tauri::command]
+pub fn clear_session_history(app_handle: AppHandle) -> CommandResult<()> {
+    let path = history_path(&app_handle).map_err(ErrorPayload::from)?;
+    match fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(VpnError::from(e).into()),
+    }
+    info!("Session history cleared");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    fn entry(
+        server_id: i32,
+        started_at: u64,
+        duration_secs: u64,
+        latency: Option<u32>,
+    ) -> SessionEntry {
+        SessionEntry {
+            server_id,
+            mode: "tun".to_string(),
+            started_at,
+            ended_at: started_at + duration_secs,
+            duration_secs,
+            bytes_up: 10,
+            bytes_down: 100,
+            peak_upload_speed: 0,
+            peak_download_speed: 0,
+            avg_latency_ms: latency,
+            reason: "user".to_string(),
+        }
+    }
+
+    #[test]
+    fn civil_date_epoch_and_negative() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn civil_date_leap_days() {
+        // 2024-02-29 = 19782 天
+        assert_eq!(civil_date(19_781), "2024-02-28");
+        assert_eq!(civil_date(19_782), "2024-02-29");
+        assert_eq!(civil_date(19_783), "2024-03-01");
+        // 2000 是闰年，1900/2100 不是
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(47_540), "2100-02-28");
+        assert_eq!(civil_date(47_541), "2100-03-01");
+    }
+
+    #[test]
+    fn civil_date_year_rollover() {
+        assert_eq!(civil_date(19_722), "2023-12-31");
+        assert_eq!(civil_date(19_723), "2024-01-01");
+        assert_eq!(civil_date(20_088), "2024-12-31");
+        assert_eq!(civil_date(20_089), "2025-01-01");
+    }
+
+    #[test]
+    fn day_of_applies_offset() {
+        // 2024-01-01 23:30 UTC
+        let e = entry(1, 19_723 * DAY + 23 * 3600 + 1800, 60, None);
+        assert_eq!(day_of(&e, 0), "2024-01-01");
+        assert_eq!(day_of(&e, 8 * 3600), "2024-01-02");
+        assert_eq!(day_of(&e, -24 * 3600), "2023-12-31");
+    }
+
+    #[test]
+    fn session_spanning_midnight_counts_on_start_day() {
+        let midnight = 19_723 * DAY;
+        let entries = vec![
+            entry(1, midnight - 1800, 3600, Some(40)),
+            entry(1, midnight + 600, 60, Some(20)),
+            entry(2, midnight - 3 * 3600, 60, None),
+        ];
+        let days = summarize(&entries, |e| day_of(e, 0));
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].key, "2023-12-31");
+        assert_eq!(days[0].sessions, 2);
+        assert_eq!(days[0].total_secs, 3660);
+        assert_eq!(days[0].avg_latency_ms, Some(40));
+        assert_eq!(days[1].key, "2024-01-01");
+        assert_eq!(days[1].sessions, 1);
+        assert_eq!(days[1].total_secs, 60);
+    }
+
+    #[test]
+    fn summarize_by_server() {
+        let entries = vec![
+            entry(1, 0, 100, Some(30)),
+            entry(2, 10, 50, None),
+            entry(1, 20, 200, Some(51)),
+        ];
+        let servers = summarize(&entries, |e| e.server_id.to_string());
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].key, "1");
+        assert_eq!(servers[0].sessions, 2);
+        assert_eq!(servers[0].total_secs, 300);
+        assert_eq!(servers[0].bytes_up, 20);
+        assert_eq!(servers[0].bytes_down, 200);
+        assert_eq!(servers[0].avg_latency_ms, Some(40));
+        assert_eq!(servers[1].key, "2");
+        assert_eq!(servers[1].avg_latency_ms, None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Kill switch 模块
+//! 连接与重连期间阻断隧道以外的出站流量，只在用户主动断开时撤销
+
+use std::net::IpAddr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::constants::kill_switch;
+use crate::error::{Result, VpnError};
+
+use super::config::ConnectConfig;
+use super::platform::{self, KillSwitchRules};
+use super::singbox::resolve_ipv4;
+use super::state::VpnState;
+
+/// Kill switch 状态（`get_kill_switch_status` 返回值）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KillSwitchStatus {
+    pub supported: bool,
+    /// 规则是否由本应用安装且尚未撤销
+    pub engaged: bool,
+    /// 实际检查结果；无权限读取规则集时为 None
+    pub verified: Option<bool>,
+    /// 放行的节点/上游代理地址
+    pub allowed_ips: Vec<String>,
+    pub engaged_at: Option<u64>,
+}
+
+/// 本次连接是否启用 kill switch（依赖 TUN 网卡与 sing-box 路由标记，SOCKS 模式不生效）
+pub fn applies_to(config: &ConnectConfig) -> bool {
+    if config.kill_switch && config.mode != "tun" {
+        warn!(
+            "Kill switch requires TUN mode, ignored in {} mode",
+            config.mode
+        );
+    }
+    config.kill_switch && config.mode == "tun"
+}
+
+/// 安装（或按新节点刷新）kill switch 规则
+pub fn engage(state: &VpnState, config: &ConnectConfig) -> Result<()> {
+    let mut allow_ips = resolve_host(&config.server_host, config.server_port);
+    if allow_ips.is_empty() {
+        return Err(VpnError::KillSwitch(format!(
+            "Cannot resolve node {}",
+            config.server_host
+        )));
+    }
+    // 备选节点同样放行，热切换后无需重装规则；前端会下发整个节点列表，并发解析并限时
+    let mut extra: Vec<(String, u16)> = config
+        .alternates
+        .iter()
+        .map(|node| (node.domain.clone(), node.port))
+        .collect();
+    if let Some(proxy) = &config.upstream {
+        extra.push((proxy.host.clone(), proxy.port));
+    }
+    allow_ips.extend(resolve_hosts(
+        extra,
+        Duration::from_millis(kill_switch::RESOLVE_TIMEOUT_MS),
+    ));
+    allow_ips.sort();
+    allow_ips.dedup();
+
+    let rules = KillSwitchRules { allow_ips };
+    platform::enable_kill_switch(&rules).map_err(VpnError::KillSwitch)?;
+
+    info!(allowed = ?rules.allow_ips, "Kill switch engaged");
+    state.set_kill_switch(KillSwitchStatus {
+        supported: true,
+        engaged: true,
+        verified: Some(true),
+        allowed_ips: rules.allow_ips.iter().map(|ip| ip.to_string()).collect(),
+        engaged_at: Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        ),
+    });
+    Ok(())
+}
+
+/// 撤销 kill switch；未安装时直接返回
+pub fn disengage(state: &VpnState) -> Result<()> {
+    if !state.get_kill_switch().engaged {
+        return Ok(());
+    }
+    platform::disable_kill_switch().map_err(VpnError::KillSwitch)?;
+    info!("Kill switch disengaged");
+    state.set_kill_switch(KillSwitchStatus::default());
+    Ok(())
+}
+
+fn resolve_host(host: &str, port: u16) -> Vec<IpAddr> {
+    match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => resolve_ipv4(host, port),
+    }
+}
+
+/// 并发解析多个地址；`timeout` 内未返回的主机跳过 (解析线程自行结束)
+fn resolve_hosts(hosts: Vec<(String, u16)>, timeout: Duration) -> Vec<IpAddr> {
+    let (tx, rx) = mpsc::channel();
+    let mut pending = 0;
+    let mut ips = Vec::new();
+    for (host, port) in hosts {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            ips.push(ip);
+            continue;
+        }
+        let tx = tx.clone();
+        pending += 1;
+        thread::spawn(move || {
+            let _ = tx.send(resolve_ipv4(&host, port));
+        });
+    }
+    drop(tx);
+
+    let deadline = Instant::now() + timeout;
+    while pending > 0 {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(resolved) => {
+                ips.extend(resolved);
+                pending -= 1;
+            }
+            Err(_) => {
+                warn!(
+                    pending,
+                    "Kill switch: hosts not resolved in time are not allowed, switching to them will be blocked"
+                );
+                break;
+            }
+        }
+    }
+    ips
+}
+
+#[tauri::command]
+pub fn get_kill_switch_status(state: tauri::State<'_, VpnState>) -> KillSwitchStatus {
+    let mut status = state.get_kill_switch();
+    status.supported = cfg!(target_os = "linux");
+    status.verified = platform::verify_kill_switch().or(status.verified);
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_hosts_keeps_literals_without_waiting() {
+        let started = Instant::now();
+        let ips = resolve_hosts(
+            vec![
+                ("203.0.113.7".to_string(), 443),
+                ("2001:db8::7".to_string(), 443),
+            ],
+            Duration::ZERO,
+        );
+        assert_eq!(
+            ips,
+            [
+                "203.0.113.7".parse::<IpAddr>().unwrap(),
+                "2001:db8::7".parse().unwrap()
+            ]
+        );
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn resolve_hosts_is_bounded_by_timeout() {
+        let hosts = (0..16)
+            .map(|i| (format!("node{}.invalid", i), 443))
+            .collect();
+        let started = Instant::now();
+        // .invalid 不会解析出地址；无论解析器多慢都不超过时限太多
+        let ips = resolve_hosts(hosts, Duration::from_millis(200));
+        assert!(ips.is_empty());
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+}
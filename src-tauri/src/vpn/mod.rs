@@ -1,12 +1,43 @@
+#[cfg(feature = "gui")]
+pub mod autoconnect;
+pub mod clash_api;
 pub mod config;
+#[cfg(feature = "gui")]
 pub mod connect;
+#[cfg(feature = "gui")]
+pub mod control;
+#[cfg(feature = "gui")]
+pub mod crashloop;
 pub mod connectivity;
+#[cfg(feature = "gui")]
 pub mod dns_leak_test;
+#[cfg(feature = "gui")]
+pub mod history;
+#[cfg(feature = "gui")]
+pub mod kill_switch;
+#[cfg(feature = "gui")]
 pub mod monitor;
+pub mod mtu;
+#[cfg(feature = "gui")]
+pub mod netwatch;
 pub mod ping;
 pub mod platform;
+#[cfg(feature = "gui")]
+pub mod profile;
 pub mod proxy;
+#[cfg(feature = "gui")]
+pub mod reconnect;
 pub mod security;
+#[cfg(feature = "gui")]
+pub mod session;
 pub mod singbox;
+#[cfg(feature = "gui")]
 pub mod state;
+#[cfg(feature = "gui")]
 pub mod stats; // 新增DNS泄漏检测
+#[cfg(feature = "gui")]
+pub mod switch;
+pub mod teardown;
+#[cfg(feature = "gui")]
+pub mod timing;
+pub mod upstream;
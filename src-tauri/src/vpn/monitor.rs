@@ -1,21 +1,15 @@
-use serde::Deserialize;
 use serde_json::json;
 use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager}; // [修复] 添加 Manager trait
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
-use super::state::{LatencyStats, TrafficStats, VpnState};
+use super::clash_api::{fetch_traffic_from_api, ClashApi};
+use super::singbox::diagnose::{self, Diagnosis};
+use super::state::{LatencyStats, StatusTransition, TrafficStats, VpnState, VpnStatusEnum};
 use crate::constants;
-
-/// sing-box connections API 响应
-#[derive(Debug, Deserialize)]
-struct ConnectionsResponse {
-    #[serde(rename = "downloadTotal")]
-    download_total: u64,
-    #[serde(rename = "uploadTotal")]
-    upload_total: u64,
-}
+use crate::error::VpnError;
 
 /// 启动流量和延迟监控
 pub fn start_monitor(app_handle: AppHandle, state: &VpnState) {
@@ -51,16 +45,9 @@ pub fn start_monitor(app_handle: AppHandle, state: &VpnState) {
                 break;
             }
 
-            // 动态获取当前模式对应的端口
+            // 动态获取本次会话的 API 凭据（端口与 secret 每次连接随机生成）
             // 因为引入了 tauri::Manager，现在 app.state() 可以正常编译了
-            let current_api_port = {
-                let vpn_state = app.state::<VpnState>();
-                if vpn_state.get_current_mode() == "tun" {
-                    constants::SINGBOX_API_PORT_TUN
-                } else {
-                    constants::SINGBOX_API_PORT_SOCKS
-                }
-            };
+            let current_api = app.state::<VpnState>().get_clash_api();
 
             // 如果之前切到了模拟数据，周期性尝试恢复真实 API（避免“一直假数据”）
             tick_count = tick_count.wrapping_add(1);
@@ -69,11 +56,13 @@ pub fn start_monitor(app_handle: AppHandle, state: &VpnState) {
                 api_fail_count = 0;
             }
 
-            // 尝试从 sing-box API 获取真实流量
+            // 尝试从 sing-box API 获取真实流量；只有真实数据计入会话历史
+            let mut from_api = false;
             let (current_download, current_upload) = if use_real_api {
-                match fetch_traffic_from_api(&client, current_api_port) {
+                match fetch_traffic_from_api(&client, current_api.as_ref()) {
                     Some((down, up)) => {
                         api_fail_count = 0;
+                        from_api = true;
                         (down, up)
                     }
                     None => {
@@ -82,8 +71,8 @@ pub fn start_monitor(app_handle: AppHandle, state: &VpnState) {
                         if api_fail_count >= 3 {
                             if api_fail_count == 3 {
                                 warn!(
-                                    "sing-box API unavailable (port {}), using simulated data",
-                                    current_api_port
+                                    "sing-box API unavailable (port {:?}), using simulated data",
+                                    current_api.as_ref().map(|api| api.port)
                                 );
                             }
                             use_real_api = false;
@@ -101,19 +90,24 @@ pub fn start_monitor(app_handle: AppHandle, state: &VpnState) {
             last_download = current_download;
             last_upload = current_upload;
 
-            let _ = app.emit(
-                "vpn-traffic",
-                TrafficStats {
-                    download_bytes: current_download,
-                    upload_bytes: current_upload,
-                    download_speed,
-                    upload_speed,
-                },
-            );
+            let traffic = TrafficStats {
+                download_bytes: current_download,
+                upload_bytes: current_upload,
+                download_speed,
+                upload_speed,
+            };
+            if from_api {
+                app.state::<VpnState>().record_traffic(&traffic);
+            }
+            let _ = app.emit("vpn-traffic", traffic);
 
             // 每 5 秒测量一次延迟
             if tick_count % 5 == 0 {
-                let latency = measure_real_latency(current_api_port);
+                let measured = measure_real_latency(current_api.as_ref());
+                if let Some(latency) = measured {
+                    app.state::<VpnState>().record_latency(latency);
+                }
+                let latency = measured.unwrap_or_else(generate_simulated_latency);
                 let _ = app.emit(
                     "vpn-latency",
                     LatencyStats {
@@ -128,27 +122,8 @@ pub fn start_monitor(app_handle: AppHandle, state: &VpnState) {
     });
 }
 
-/// 从 sing-box API 获取真实流量统计
-fn fetch_traffic_from_api(
-    client: &Option<reqwest::blocking::Client>,
-    port: u16,
-) -> Option<(u64, u64)> {
-    let client = client.as_ref()?;
-    let url = format!("http://127.0.0.1:{}/connections", port);
-
-    let response = client.get(&url).send().ok()?;
-    if !response.status().is_success() {
-        return None;
-    }
-
-    let text = response.text().ok()?;
-    serde_json::from_str::<ConnectionsResponse>(&text)
-        .ok()
-        .map(|conn| (conn.download_total, conn.upload_total))
-}
-
-/// 测量真实延迟 - 通过代理测试
-fn measure_real_latency(port: u16) -> u32 {
+/// 测量真实延迟 - 通过代理测试；均失败时返回 None
+fn measure_real_latency(api: Option<&ClashApi>) -> Option<u32> {
     // 方案 1: 通过 SOCKS 代理测试
     let proxy = reqwest::Proxy::all(format!(
         "socks5://127.0.0.1:{}",
@@ -164,28 +139,28 @@ fn measure_real_latency(port: u16) -> u32 {
             let start = Instant::now();
             if let Ok(response) = client.get("http://www.gstatic.com/generate_204").send() {
                 if response.status().is_success() || response.status().as_u16() == 204 {
-                    return start.elapsed().as_millis() as u32;
+                    return Some(start.elapsed().as_millis() as u32);
                 }
             }
         }
     }
 
     // 方案 2: 通过 sing-box API 测试
-    if let Ok(client) = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
-    {
-        let url = format!(
-            "http://127.0.0.1:{}/proxies/proxy/delay?timeout=3000&url=http://www.gstatic.com/generate_204",
-            port
-        );
-
-        if let Ok(response) = client.get(&url).send() {
+    if let (Some(api), Ok(client)) = (
+        api,
+        reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build(),
+    ) {
+        let url =
+            api.url("/proxies/proxy/delay?timeout=3000&url=http://www.gstatic.com/generate_204");
+
+        if let Ok(response) = client.get(&url).bearer_auth(&api.secret).send() {
             if response.status().is_success() {
                 if let Ok(text) = response.text() {
                     if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
                         if let Some(delay) = json.get("delay").and_then(|d| d.as_u64()) {
-                            return delay as u32;
+                            return Some(delay as u32);
                         }
                     }
                 }
@@ -193,8 +168,7 @@ fn measure_real_latency(port: u16) -> u32 {
         }
     }
 
-    // 方案 3: 返回模拟值
-    generate_simulated_latency()
+    None
 }
 
 /// 生成模拟流量数据（备用）
@@ -219,12 +193,81 @@ pub fn stop_watchdog(state: &VpnState) {
     state.watchdog_running.store(false, Ordering::SeqCst);
 }
 
+/// 发送 `vpn-connection-error` 事件，附带结构化错误与 sing-box 输出诊断
+pub fn emit_connection_error(
+    app_handle: &AppHandle,
+    error: &VpnError,
+    diagnosis: Option<&Diagnosis>,
+    fatal: bool,
+) {
+    let _ = app_handle.emit(
+        "vpn-connection-error",
+        json!({
+            "error": error.to_string(),
+            "fatal": fatal,
+            "payload": error.payload(),
+            "diagnosis": diagnosis,
+        }),
+    );
+}
+
 /// 发送状态变更事件
 pub fn emit_status_change(app_handle: &AppHandle, state: &VpnState) {
     let status_result = state.get_status_result();
     let _ = app_handle.emit("vpn-status-change", status_result);
 }
 
+/// 执行状态迁移并发送 `vpn-state-transition` 与 `vpn-status-change` 事件
+pub fn transition_status(
+    app_handle: &AppHandle,
+    state: &VpnState,
+    to: VpnStatusEnum,
+    reason: &str,
+) -> crate::error::Result<()> {
+    match state.transition(to, reason) {
+        Ok(record) => {
+            announce_transition(app_handle, state, &record);
+            Ok(())
+        }
+        Err(e) => {
+            warn!("Rejected state transition: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// 迁移到 Connecting / Reconnecting 并开始一次连接尝试，返回其取消令牌；迁移被拒绝时
+/// 进行中的尝试保持不变。事件与 `transition_status` 相同
+pub fn begin_attempt(
+    app_handle: &AppHandle,
+    state: &VpnState,
+    to: VpnStatusEnum,
+    reason: &str,
+) -> crate::error::Result<CancellationToken> {
+    match state.begin_connect_attempt(to, reason) {
+        Ok((record, token)) => {
+            announce_transition(app_handle, state, &record);
+            Ok(token)
+        }
+        Err(e) => {
+            warn!("Rejected state transition: {}", e);
+            Err(e)
+        }
+    }
+}
+
+fn announce_transition(app_handle: &AppHandle, state: &VpnState, record: &StatusTransition) {
+    info!(
+        from = record.from.as_str(),
+        to = record.to.as_str(),
+        reason = %record.reason,
+        "State transition"
+    );
+    super::history::on_transition(app_handle, state, record);
+    let _ = app_handle.emit("vpn-state-transition", record);
+    emit_status_change(app_handle, state);
+}
+
 /// 启动进程监控 (watchdog)
 pub fn start_process_watchdog(app_handle: AppHandle, state: &VpnState) {
     if state.watchdog_running.swap(true, Ordering::SeqCst) {
@@ -234,7 +277,6 @@ pub fn start_process_watchdog(app_handle: AppHandle, state: &VpnState) {
 
     let app = app_handle.clone();
     let watchdog_flag = state.watchdog_running.clone();
-    let user_disconnect = state.get_user_disconnect_flag();
 
     std::thread::spawn(move || {
         info!("Process watchdog started");
@@ -247,8 +289,9 @@ pub fn start_process_watchdog(app_handle: AppHandle, state: &VpnState) {
                 break;
             }
 
-            // 用户主动断开，不检查
-            if user_disconnect.load(Ordering::SeqCst) {
+            // 已不在 Connected 状态（用户断开/重连中），不检查
+            let vpn_state = app.state::<VpnState>();
+            if vpn_state.get_status() != VpnStatusEnum::Connected {
                 break;
             }
 
@@ -261,13 +304,39 @@ pub fn start_process_watchdog(app_handle: AppHandle, state: &VpnState) {
                 );
 
                 if consecutive_failures >= 3 {
-                    let _ = app.emit(
-                        "vpn-process-crashed",
-                        json!({
-                            "reason": "process_died",
-                            "consecutive_failures": consecutive_failures
-                        }),
+                    let reason = "sing-box process died";
+                    let diagnosis = diagnose::diagnose_tun_log();
+                    let error = diagnosis
+                        .as_ref()
+                        .map(|d| d.to_error())
+                        .unwrap_or_else(|| VpnError::Connection(reason.to_string()));
+                    let crash_loop = super::crashloop::record_exit(
+                        &app,
+                        &vpn_state,
+                        vpn_state.get_server_id().unwrap_or_default(),
+                        &vpn_state.get_current_mode(),
+                        &error,
+                        reason,
                     );
+                    if let (false, Some(diagnosis)) = (crash_loop, &diagnosis) {
+                        emit_connection_error(&app, &error, Some(diagnosis), true);
+                    }
+                    super::connect::fast_cleanup_connection(&app, &vpn_state);
+                    if crash_loop || !super::reconnect::handle_unexpected_exit(&app, reason) {
+                        let reason = if crash_loop {
+                            format!("crash loop: {}", reason)
+                        } else {
+                            reason.to_string()
+                        };
+                        let _ = transition_status(&app, &vpn_state, VpnStatusEnum::Failed, &reason);
+                        let _ = app.emit(
+                            "vpn-process-crashed",
+                            json!({
+                                "reason": if crash_loop { "crash_loop" } else { "process_died" },
+                                "consecutive_failures": consecutive_failures
+                            }),
+                        );
+                    }
                     break;
                 }
             } else {
@@ -280,23 +349,9 @@ pub fn start_process_watchdog(app_handle: AppHandle, state: &VpnState) {
     });
 }
 
-/// 检查 sing-box 进程是否存活
+/// 检查本应用启动的 sing-box 进程是否存活（按 pid + 启动时间识别，不受其他 sing-box 实例干扰）
 fn is_singbox_alive() -> bool {
-    #[cfg(target_os = "macos")]
-    {
-        use std::process::Command;
-
-        if let Ok(output) = Command::new("pgrep").arg("-x").arg("sing-box").output() {
-            return output.status.success();
-        }
-        false
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        // 其他平台：目前保守返回 true（避免误报）
-        true
-    }
+    super::platform::owned::any_alive()
 }
 
 /// 发送日志事件
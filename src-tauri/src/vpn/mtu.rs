@@ -0,0 +1,364 @@
+//! Path-MTU 探测模块
+//! MTU 设为 auto (0) 时探测到节点的可用路径 MTU，扣除 QUIC/hysteria2 开销后用于 TUN 入站，
+//! 结果按 "网络 + 节点" 缓存，避免每次连接都重新探测
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::constants::{mtu, MTU_MAX};
+
+use super::singbox::resolve_ipv4;
+
+const CACHE_FILE: &str = "mtu-cache.json";
+
+/// 一次 MTU 测量结果（同时推送给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtuMeasurement {
+    /// 到节点的路径 MTU (IP 层)
+    pub path_mtu: u16,
+    /// 扣除隧道开销后用于 TUN 的 MTU
+    pub tun_mtu: u16,
+    /// 网络标识 (默认出口网卡 + 网关)
+    pub network: String,
+    /// 是否来自缓存
+    pub cached: bool,
+    pub measured_at: u64,
+}
+
+/// 确定 TUN MTU：优先读缓存，否则实际探测；失败时返回 None 由调用方回退到默认值
+pub fn resolve_tun_mtu(cache_dir: &Path, host: &str, port: u16) -> Option<MtuMeasurement> {
+    let node_ip = match host.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => *resolve_ipv4(host, port).first()?,
+    };
+    let network = current_network_id();
+    let key = format!("{}->{}", network, node_ip);
+    let now = now_secs();
+
+    let cache_path = cache_dir.join(CACHE_FILE);
+    let mut cache = load_cache(&cache_path);
+
+    if let Some(hit) = cache.get(&key) {
+        if now.saturating_sub(hit.measured_at) < mtu::CACHE_TTL_SECS {
+            debug!(key = %key, path_mtu = hit.path_mtu, "Using cached path MTU");
+            return Some(MtuMeasurement {
+                cached: true,
+                ..hit.clone()
+            });
+        }
+    }
+
+    let path_mtu = probe_path_mtu(node_ip, port)?;
+    let measurement = MtuMeasurement {
+        path_mtu,
+        tun_mtu: tun_mtu_for_path(path_mtu),
+        network,
+        cached: false,
+        measured_at: now,
+    };
+    info!(
+        path_mtu = measurement.path_mtu,
+        tun_mtu = measurement.tun_mtu,
+        "Path MTU measured"
+    );
+
+    cache.insert(key, measurement.clone());
+    save_cache(&cache_path, &cache);
+
+    Some(measurement)
+}
+
+/// 路径 MTU 扣除 IP/UDP + QUIC + hysteria2 封装开销
+pub fn tun_mtu_for_path(path_mtu: u16) -> u16 {
+    path_mtu
+        .saturating_sub(mtu::IP_UDP_HEADER + mtu::QUIC_OVERHEAD + mtu::HYSTERIA_OVERHEAD)
+        .clamp(mtu::TUN_MIN, MTU_MAX)
+}
+
+fn load_cache(path: &Path) -> HashMap<String, MtuMeasurement> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(path: &Path, cache: &HashMap<String, MtuMeasurement>) {
+    match serde_json::to_string_pretty(cache) {
+        Ok(json) => {
+            if let Err(e) = fs::write(path, json) {
+                warn!("Failed to write MTU cache: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize MTU cache: {}", e),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// 当前网络标识：默认路由出口网卡 + 网关地址
+#[cfg(target_os = "linux")]
+fn current_network_id() -> String {
+    default_route()
+        .map(|(iface, gateway)| format!("{}@{}", iface, gateway))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_network_id() -> String {
+    "default".to_string()
+}
+
+/// 解析 /proc/net/route 中的默认路由 (网卡, 网关)
+#[cfg(target_os = "linux")]
+fn default_route() -> Option<(String, std::net::Ipv4Addr)> {
+    let table = fs::read_to_string("/proc/net/route").ok()?;
+    table.lines().skip(1).find_map(|line| {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 3 || cols[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(cols[2], 16).ok()?;
+        // 字段按主机字节序打印
+        Some((
+            cols[0].to_string(),
+            std::net::Ipv4Addr::from(gateway.to_ne_bytes()),
+        ))
+    })
+}
+
+/// 探测到节点的路径 MTU
+///
+/// 以 DF 位发送 QUIC 探测包，只有收到节点应答的尺寸才算可达：先试本地上限，不通时在
+/// [`mtu::PROBE_FLOOR`, 上限] 内二分查找。途中丢弃大包而不回 ICMP 的路径同样能测出；
+/// 下限都没有应答时 (节点不响应探测或 UDP 被封锁) 结果未知，返回 None，不缓存
+#[cfg(target_os = "linux")]
+fn probe_path_mtu(node_ip: IpAddr, port: u16) -> Option<u16> {
+    use std::net::UdpSocket;
+    use std::os::unix::io::AsRawFd;
+
+    if !node_ip.is_ipv4() {
+        return None;
+    }
+
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect((node_ip, port)).ok()?;
+    let fd = socket.as_raw_fd();
+
+    let pmtudisc: libc::c_int = libc::IP_PMTUDISC_DO;
+    // SAFETY: fd 由 socket 持有且有效，optval 指向栈上的 c_int
+    let rc = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            &pmtudisc as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if rc != 0 {
+        warn!(
+            "IP_MTU_DISCOVER not supported: {}",
+            std::io::Error::last_os_error()
+        );
+        return None;
+    }
+
+    let upper = read_ip_mtu(fd).unwrap_or(MTU_MAX).min(MTU_MAX);
+    if upper < mtu::PROBE_FLOOR || !probe_size(&socket, mtu::PROBE_FLOOR) {
+        debug!("No reply to MTU probes, path MTU unknown");
+        return None;
+    }
+    Some(search_path_mtu(
+        mtu::PROBE_FLOOR,
+        upper,
+        mtu::PROBE_STEP,
+        |size| probe_size(&socket, size),
+    ))
+}
+
+/// 发送 IP 层尺寸为 `size` 的探测包并等待应答；无应答时重发
+#[cfg(target_os = "linux")]
+fn probe_size(socket: &std::net::UdpSocket, size: u16) -> bool {
+    use std::time::{Duration, Instant};
+
+    let mut buf = [0u8; 2048];
+    for _ in 0..mtu::PROBE_ATTEMPTS {
+        let id: [u8; 8] = rand::random();
+        let packet = probe_packet(&id, size.saturating_sub(mtu::IP_UDP_HEADER) as usize);
+        match socket.send(&packet) {
+            Ok(_) => {}
+            // 超出本地网卡或内核已从 ICMP 得知的 PMTU
+            Err(e) if e.raw_os_error() == Some(libc::EMSGSIZE) => return false,
+            Err(e) => {
+                debug!(size, "MTU probe send failed: {}", e);
+                return false;
+            }
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(mtu::PROBE_WAIT_MS);
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            if socket
+                .set_read_timeout(Some(left.max(Duration::from_millis(1))))
+                .is_err()
+            {
+                return false;
+            }
+            match socket.recv(&mut buf) {
+                Ok(n) if is_version_negotiation(&buf[..n], &id) => return true,
+                // 之前探测包的迟到应答
+                Ok(_) => continue,
+                // 超时或 ICMP 错误
+                Err(_) => break,
+            }
+        }
+    }
+    false
+}
+
+/// 强制版本协商的保留版本号 (RFC 9000 §15 的 0x?a?a?a?a 形式)
+#[cfg(any(target_os = "linux", test))]
+const PROBE_VERSION: [u8; 4] = [0x1a, 0x2a, 0x3a, 0x4a];
+
+/// QUIC 长包头探测包，填充到 `len` 字节：未知版本号使服务器回复版本协商包，
+/// 目标/源连接 ID 均为 `id`，据此识别应答
+#[cfg(any(target_os = "linux", test))]
+fn probe_packet(id: &[u8; 8], len: usize) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(len);
+    packet.push(0xc0);
+    packet.extend_from_slice(&PROBE_VERSION);
+    for _ in 0..2 {
+        packet.push(id.len() as u8);
+        packet.extend_from_slice(id);
+    }
+    packet.resize(len.max(packet.len()), 0);
+    packet
+}
+
+/// 版本协商包：长包头、版本号为 0，目标连接 ID 为探测包的源连接 ID
+#[cfg(any(target_os = "linux", test))]
+fn is_version_negotiation(reply: &[u8], id: &[u8; 8]) -> bool {
+    reply.len() >= 6 + id.len()
+        && reply[0] & 0x80 != 0
+        && reply[1..5] == [0, 0, 0, 0]
+        && reply[5] as usize == id.len()
+        && reply[6..6 + id.len()] == id[..]
+}
+
+/// 在 [`lo`, `hi`] 内查找可达的最大尺寸，`lo` 须已确认可达；结果与真实值相差不超过 `step`
+#[cfg(any(target_os = "linux", test))]
+fn search_path_mtu(mut lo: u16, mut hi: u16, step: u16, mut probe: impl FnMut(u16) -> bool) -> u16 {
+    // 多数网络上限直接可达
+    if hi <= lo || probe(hi) {
+        return hi.max(lo);
+    }
+    hi -= 1;
+    while hi.saturating_sub(lo) >= step {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if probe(mid) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    lo
+}
+
+#[cfg(target_os = "linux")]
+fn read_ip_mtu(fd: std::os::unix::io::RawFd) -> Option<u16> {
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: value/len 均为有效的栈变量
+    let rc = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::IPPROTO_IP,
+            libc::IP_MTU,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if rc == 0 && value > 0 {
+        u16::try_from(value).ok()
+    } else {
+        None
+    }
+}
+
+/// 其他平台暂不支持读取内核 PMTU，回退到默认 MTU
+#[cfg(not(target_os = "linux"))]
+fn probe_path_mtu(_node_ip: IpAddr, _port: u16) -> Option<u16> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟路径：不超过 `path_mtu` 的探测包才有应答
+    fn search(path_mtu: u16, upper: u16) -> (u16, u32) {
+        let mut probes = 0;
+        let found = search_path_mtu(mtu::PROBE_FLOOR, upper, mtu::PROBE_STEP, |size| {
+            probes += 1;
+            size <= path_mtu
+        });
+        (found, probes)
+    }
+
+    #[test]
+    fn upper_bound_reachable() {
+        assert_eq!(search(1500, 1500), (1500, 1));
+        assert_eq!(search(1500, 1400), (1400, 1));
+    }
+
+    #[test]
+    fn finds_black_hole_limit() {
+        for path_mtu in [mtu::PROBE_FLOOR, 1280, 1380, 1420, 1499] {
+            let (found, probes) = search(path_mtu, 1500);
+            assert!(found <= path_mtu, "{} > {}", found, path_mtu);
+            assert!(
+                path_mtu - found < mtu::PROBE_STEP,
+                "{} vs {}",
+                found,
+                path_mtu
+            );
+            assert!(probes <= 8, "{} probes", probes);
+        }
+    }
+
+    #[test]
+    fn never_below_confirmed_floor() {
+        assert_eq!(search(0, 1500).0, mtu::PROBE_FLOOR);
+        assert_eq!(search(0, mtu::PROBE_FLOOR).0, mtu::PROBE_FLOOR);
+    }
+
+    #[test]
+    fn probe_packet_round_trip() {
+        let id = [1, 2, 3, 4, 5, 6, 7, 8];
+        let packet = probe_packet(&id, 1200);
+        assert_eq!(packet.len(), 1200);
+        assert_eq!(packet[0] & 0xc0, 0xc0);
+        assert_eq!(packet[1..5], PROBE_VERSION);
+
+        // 版本协商包：目标连接 ID 为探测包的源连接 ID，随后是服务器支持的版本列表
+        let mut reply = vec![0x80, 0, 0, 0, 0, 8];
+        reply.extend_from_slice(&id);
+        reply.push(8);
+        reply.extend_from_slice(&id);
+        reply.extend_from_slice(&[0, 0, 0, 1]);
+        assert!(is_version_negotiation(&reply, &id));
+        assert!(!is_version_negotiation(&reply, &[9; 8]));
+        assert!(!is_version_negotiation(&packet, &id));
+        assert!(!is_version_negotiation(&reply[..10], &id));
+    }
+}
@@ -0,0 +1,184 @@
+//! 网络变化处理模块
+//! Wi-Fi / 有线切换或休眠唤醒后，sing-box 仍持有失效的连接，仅靠监控轮询无法恢复。
+//! 收到系统网络事件并等待其平息后：SOCKS 模式在开启自动重连时快速重连；TUN 模式通过 Clash API 关闭现有连接
+//! (重启 TUN 需要再次提权，sing-box 会自行跟随默认网卡变化)。
+//! 同一监听也驱动自动连接策略，网络标识变化时先由其决定连接或断开
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, info, warn};
+
+use crate::constants::netwatch;
+use crate::error::{Result, VpnError};
+
+use super::autoconnect;
+use super::clash_api::ClashApi;
+use super::monitor::emit_log;
+use super::platform::{self, NetworkChange, NetworkIdentity};
+use super::reconnect;
+use super::state::{VpnState, VpnStatusEnum};
+
+/// `vpn-network-change` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkChangeEvent {
+    /// resume / default_route / network_changed
+    pub reason: &'static str,
+    /// reconnect / reset_connections / none
+    pub action: &'static str,
+    pub detail: String,
+}
+
+fn emit_network_change(app: &AppHandle, reason: &'static str, action: &'static str, detail: &str) {
+    let _ = app.emit(
+        "vpn-network-change",
+        NetworkChangeEvent {
+            reason,
+            action,
+            detail: detail.to_string(),
+        },
+    );
+}
+
+/// 关闭 sing-box 当前所有连接，应用随后按新网络重新建立
+async fn reset_connections(api: &ClashApi) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()?;
+
+    let response = client
+        .delete(api.url("/connections"))
+        .bearer_auth(&api.secret)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(VpnError::Network(format!(
+            "Clash API rejected connection reset: {}",
+            response.status()
+        )))
+    }
+}
+
+/// 一批事件是否需要处理；返回原因标识与说明
+fn assess(
+    changes: &[NetworkChange],
+    before: Option<&NetworkIdentity>,
+    after: Option<&NetworkIdentity>,
+) -> Option<(&'static str, String)> {
+    if changes.contains(&NetworkChange::Resume) {
+        return Some(("resume", "system resumed from sleep".to_string()));
+    }
+    if before != after {
+        let interfaces = after.map(|n| n.interfaces.join(", ")).unwrap_or_default();
+        return Some((
+            "network_changed",
+            format!("active interfaces: [{}]", interfaces),
+        ));
+    }
+    if changes.contains(&NetworkChange::DefaultRoute) {
+        return Some(("default_route", "default route changed".to_string()));
+    }
+    // 网卡状态/地址抖动但活动网络未变化，无需处理
+    None
+}
+
+async fn handle(app: &AppHandle, reason: &'static str, detail: &str) {
+    let state = app.state::<VpnState>();
+    if state.get_status() != VpnStatusEnum::Connected {
+        debug!(reason, "Network changed while not connected");
+        emit_network_change(app, reason, "none", detail);
+        return;
+    }
+
+    if state.get_current_mode() == "tun" {
+        let Some(api) = state.get_clash_api() else {
+            return;
+        };
+        info!(reason, detail = %detail, "Network changed, resetting connections");
+        emit_network_change(app, reason, "reset_connections", detail);
+        match reset_connections(&api).await {
+            Ok(()) => emit_log(
+                app,
+                "info",
+                &format!("Network changed ({}), connections reset", detail),
+            ),
+            Err(e) => warn!("Connection reset failed: {}", e),
+        }
+        return;
+    }
+
+    // SOCKS 模式需要重建会话，与意外断开一样受自动重连开关控制
+    if !reconnect::is_enabled(app) {
+        info!(reason, detail = %detail, "Network changed, auto-reconnect disabled");
+        emit_network_change(app, reason, "none", detail);
+        return;
+    }
+
+    info!(reason, detail = %detail, "Network changed, reconnecting");
+    emit_network_change(app, reason, "reconnect", detail);
+    let message = format!("network changed: {}", detail);
+    if !reconnect::reconnect_now(app, &message).await {
+        warn!("Fast reconnect unavailable after network change");
+    }
+}
+
+/// setup 阶段调用：监听系统网络事件，平台不支持时不启动
+pub fn start(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    if !platform::watch_network_changes(tx) {
+        info!("Network change watcher unavailable on this platform");
+        return;
+    }
+
+    std::thread::spawn(move || {
+        info!("Network change watcher started");
+        let settle = Duration::from_millis(netwatch::SETTLE_MS);
+        let cooldown = Duration::from_secs(netwatch::COOLDOWN_SECS);
+        let mut last_network = platform::current_network();
+        let mut quiet_until: Option<Instant> = None;
+
+        while let Ok(first) = rx.recv() {
+            // 合并切换过程中的连串事件，直到静默 settle 时长
+            let mut changes = vec![first];
+            loop {
+                match rx.recv_timeout(settle) {
+                    Ok(change) => changes.push(change),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            let network = platform::current_network();
+            let assessment = assess(&changes, last_network.as_ref(), network.as_ref());
+            let identity_changed = network != last_network;
+            last_network = network;
+
+            // 网络标识变化先交给自动连接策略；它已连接或断开时不再对同一变化重连
+            if let Some(network) = last_network.as_ref().filter(|_| identity_changed) {
+                if tauri::async_runtime::block_on(autoconnect::apply_network(&app, network)) {
+                    quiet_until = Some(Instant::now() + cooldown);
+                    continue;
+                }
+            }
+
+            // 冷却期内只记录最新网络；唤醒事件总是处理
+            let cooling = quiet_until.is_some_and(|t| Instant::now() < t);
+            let Some((reason, detail)) = assessment else {
+                continue;
+            };
+            if cooling && reason != "resume" {
+                debug!(reason, "Network change ignored during cooldown");
+                continue;
+            }
+
+            tauri::async_runtime::block_on(handle(&app, reason, &detail));
+            quiet_until = Some(Instant::now() + cooldown);
+        }
+        info!("Network change watcher stopped");
+    });
+}
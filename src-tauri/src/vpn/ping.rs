@@ -1,7 +1,9 @@
 use std::net::{TcpStream, ToSocketAddrs};
 use std::time::{Duration, Instant};
+#[cfg(feature = "gui")]
 use tauri::{AppHandle, Emitter};
 use serde::Serialize;
+#[cfg(feature = "gui")]
 use tracing::debug;
 
 /// Ping 结果
@@ -49,7 +51,18 @@ fn get_status_from_latency(latency: i32) -> &'static str {
     }
 }
 
+/// 测试单个节点并给出状态（命令行客户端与界面共用）
+pub fn ping_node(node_id: i32, domain: &str, port: u16) -> PingResult {
+    let latency = tcp_ping(domain, port, 5000);
+    PingResult {
+        node_id,
+        latency_ms: latency,
+        status: get_status_from_latency(latency).to_string(),
+    }
+}
+
 /// 批量测试节点延迟
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn ping_nodes(
     app_handle: AppHandle,
@@ -61,17 +74,8 @@ pub async fn ping_nodes(
     let handles: Vec<_> = nodes.into_iter().map(|(id, domain, port)| {
         let app = app_handle.clone();
         std::thread::spawn(move || {
-            let latency = tcp_ping(&domain, port, 5000);
-            let status = get_status_from_latency(latency);
-            
-            let result = PingResult {
-                node_id: id,
-                latency_ms: latency,
-                status: status.to_string(),
-            };
-            
             // 发送单个节点的结果
-            let _ = app.emit("ping-result", result);
+            let _ = app.emit("ping-result", ping_node(id, &domain, port));
         })
     }).collect();
     
@@ -84,7 +88,7 @@ pub async fn ping_nodes(
 }
 
 /// 测试单个节点延迟（同步返回）
-#[tauri::command]
+#[cfg_attr(feature = "gui", tauri::command)]
 pub fn ping_single_node(domain: String, port: u16) -> i32 {
     tcp_ping(&domain, port, 5000)
 }
\ No newline at end of file
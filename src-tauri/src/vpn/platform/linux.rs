@@ -2,27 +2,15 @@
 
 use std::fs;
 use std::path::Path;
-use std::process::Command;
-use crate::constants::SINGBOX_PID_FILE;
-use super::TunPrecheck;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+use crate::constants::{get_singbox_pid_file, kill_switch, teardown, tun};
+use super::{owned, KillSwitchRules, NetworkChange, NetworkIdentity, NetworkSnapshot, TunPrecheck};
 
-/// 检查 sing-box 进程是否在运行
+/// 检查本应用启动的 sing-box 是否在运行
 pub fn is_singbox_running() -> bool {
-    if let Ok(pid_str) = fs::read_to_string(SINGBOX_PID_FILE) {
-        if let Ok(pid) = pid_str.trim().parse::<i32>() {
-            return Command::new("kill")
-                .args(["-0", &pid.to_string()])
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
-        }
-    }
-    Command::new("pgrep")
-        .arg("-x")
-        .arg("sing-box")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+    owned::any_alive()
 }
 
 /// 检查 sing-box 是否已安装
@@ -49,77 +37,95 @@ pub fn precheck_tun_permission() -> TunPrecheck {
 }
 
 /// 以 root 权限运行 sing-box (TUN 模式)
-pub fn run_singbox_tun_as_root(config_path: &str, _log_file: &str) -> Result<(), String> {
+pub fn run_singbox_tun_as_root(config_path: &str, log_file: &str) -> Result<(), String> {
     if is_singbox_running() {
         let _ = stop_singbox_tun_as_root();
         std::thread::sleep(std::time::Duration::from_millis(500));
     }
 
+    // pkexec 与 sing-box 的输出写入日志文件，供启动失败诊断读取
+    let log = fs::File::create(log_file).map_err(|e| format!("Failed to create log: {}", e))?;
+    let log_err = log
+        .try_clone()
+        .map_err(|e| format!("Failed to create log: {}", e))?;
+
     let output = Command::new("pkexec")
         .args(["sing-box", "run", "-c", config_path])
+        .stdout(log)
+        .stderr(log_err)
         .spawn();
 
     match output {
         Ok(mut child) => {
-            if let Some(pid) = child.id() {
-                let _ = fs::write(SINGBOX_PID_FILE, pid.to_string());
-            }
-            std::thread::sleep(std::time::Duration::from_millis(1000));
-            if is_singbox_running() {
-                Ok(())
-            } else {
-                Err("sing-box failed to start".to_string())
+            let _ = fs::write(get_singbox_pid_file(), child.id().to_string());
+            // pkexec 认证后 exec 为 sing-box，pid 与启动时间不变
+            owned::register(child.id(), true);
+            // 认证提示期间进程同样存活，等到 TUN 网卡建立或 pkexec/sing-box 退出为止
+            let deadline = std::time::Instant::now()
+                + std::time::Duration::from_secs(tun::START_TIMEOUT_SECS);
+            while std::time::Instant::now() < deadline {
+                if let Ok(Some(status)) = child.try_wait() {
+                    return Err(start_failure(status.code()));
+                }
+                if tun_interface_present() {
+                    return Ok(());
+                }
+                std::thread::sleep(std::time::Duration::from_millis(200));
             }
+            Err(format!(
+                "sing-box did not bring up {} within {}s (authorization pending?)",
+                tun::INTERFACE_NAME,
+                tun::START_TIMEOUT_SECS
+            ))
         }
         Err(e) => Err(format!("Failed to start sing-box: {}", e)),
     }
 }
 
-/// 停止 sing-box (TUN 模式)
-pub fn stop_singbox_tun_as_root() -> Result<(), String> {
-    if let Ok(pid_str) = fs::read_to_string(SINGBOX_PID_FILE) {
-        if let Ok(pid) = pid_str.trim().parse::<i32>() {
-            let _ = Command::new("kill").args(["-TERM", &pid.to_string()]).output();
-            let _ = fs::remove_file(SINGBOX_PID_FILE);
-        }
+/// pkexec 或 sing-box 在 TUN 网卡建立前退出时的错误
+fn start_failure(code: Option<i32>) -> String {
+    // pkexec 约定：126 为用户取消授权，127 为未获授权
+    match code {
+        Some(126) => "pkexec: authorization dismissed".to_string(),
+        Some(127) => "pkexec: not authorized".to_string(),
+        Some(code) => format!("sing-box failed to start (exit code {})", code),
+        None => "sing-box failed to start".to_string(),
     }
+}
 
-    if is_singbox_running() {
-        let _ = Command::new("pkill").args(["-TERM", "sing-box"]).output();
+/// 停止 sing-box (TUN 模式)；只结束本应用登记过的进程，SIGTERM 超时后 SIGKILL
+pub fn stop_singbox_tun_as_root() -> Result<(), String> {
+    let (_, leftover) = owned::terminate_all(std::time::Duration::from_millis(teardown::STOP_GRACE_MS))?;
+    let _ = fs::remove_file(get_singbox_pid_file());
+    if leftover.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("sing-box still running: {:?}", leftover))
     }
-    Ok(())
 }
 
 /// 清理 TUN 路由
 pub fn cleanup_tun_routes() {
-    // 删除 tun0 相关路由
+    // 删除 TUN 网卡相关路由
     let _ = Command::new("ip")
-        .args(["route", "del", "default", "dev", "tun0"])
+        .args(["route", "del", "default", "dev", tun::INTERFACE_NAME])
         .output();
     
     // 删除 tun 接口
     let _ = Command::new("ip")
-        .args(["link", "del", "tun0"])
+        .args(["link", "del", tun::INTERFACE_NAME])
         .output();
 }
 
-/// 恢复默认网关
-pub fn restore_default_gateway() {
-    // 恢复 DNS 配置
-    if Path::new("/etc/resolv.conf.bak").exists() {
-        let _ = fs::copy("/etc/resolv.conf.bak", "/etc/resolv.conf");
-        let _ = fs::remove_file("/etc/resolv.conf.bak");
-    }
-}
-
 /// 强制清理所有 TUN 相关资源
 pub fn force_cleanup() {
     if is_singbox_running() {
         let _ = stop_singbox_tun_as_root();
     }
-    let _ = fs::remove_file(SINGBOX_PID_FILE);
-    cleanup_tun_routes();
-    restore_default_gateway();
+    let _ = fs::remove_file(get_singbox_pid_file());
+    if tun_interface_present() {
+        cleanup_tun_routes();
+    }
 }
 
 /// 设置系统 SOCKS 代理
@@ -158,3 +164,464 @@ fn detect_desktop_environment() -> Option<String> {
     std::env::var("XDG_CURRENT_DESKTOP").ok()
 }
 
+/// 检查 nft 是否可用
+fn check_nft_available() -> bool {
+    Command::new("which")
+        .arg("nft")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// 生成 kill switch 规则集
+///
+/// 先声明再删除同名表，使 `nft -f` 原子替换旧规则
+fn build_kill_switch_ruleset(rules: &KillSwitchRules) -> String {
+    let (v4, v6): (Vec<_>, Vec<_>) = rules.allow_ips.iter().partition(|ip| ip.is_ipv4());
+    let join = |ips: &[&std::net::IpAddr]| {
+        ips.iter()
+            .map(|ip| ip.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut chain = vec![
+        "type filter hook output priority 0; policy drop;".to_string(),
+        "oifname \"lo\" accept".to_string(),
+        format!("oifname \"{}\" accept", tun::INTERFACE_NAME),
+        format!("meta mark {:#x} accept", kill_switch::BYPASS_MARK),
+    ];
+    if !v4.is_empty() {
+        chain.push(format!("ip daddr {{ {} }} accept", join(&v4)));
+    }
+    if !v6.is_empty() {
+        chain.push(format!("ip6 daddr {{ {} }} accept", join(&v6)));
+    }
+    chain.push(format!(
+        "ip daddr {{ {} }} accept",
+        kill_switch::LAN_V4.join(", ")
+    ));
+    chain.push(format!(
+        "ip6 daddr {{ {} }} accept",
+        kill_switch::LAN_V6.join(", ")
+    ));
+    // DHCP / DHCPv6 续租
+    chain.push("udp sport 68 udp dport 67 accept".to_string());
+    chain.push("udp sport 546 udp dport 547 accept".to_string());
+
+    format!(
+        "table inet {table}\ndelete table inet {table}\ntable inet {table} {{\n    chain output {{\n        {rules}\n    }}\n}}\n",
+        table = kill_switch::TABLE,
+        rules = chain.join("\n        ")
+    )
+}
+
+/// 安装 kill switch 规则并确认已生效
+///
+/// 规则集经标准输入交给 root 的 nft，不落盘：写入用户可写目录的文件在授权弹窗期间可被同一用户的
+/// 其他进程替换。安装与撤销各需一次 pkexec 授权，与 sing-box 的启动/停止授权相互独立
+pub fn enable_kill_switch(rules: &KillSwitchRules) -> Result<(), String> {
+    if !check_nft_available() {
+        return Err("nftables (nft) is not installed".to_string());
+    }
+
+    // 同一次提权内加载并回读，避免二次授权
+    let script = format!("nft -f - && nft list table inet {}", kill_switch::TABLE);
+    let mut child = Command::new("pkexec")
+        .args(["/bin/sh", "-c", &script])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // 授权被取消时 pkexec 不读取输入，写入失败以退出码为准
+        let _ = stdin.write_all(build_kill_switch_ruleset(rules).as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "nft failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+    if listing.contains("policy drop") && listing.contains(tun::INTERFACE_NAME) {
+        Ok(())
+    } else {
+        Err("Kill switch rules not found after install".to_string())
+    }
+}
+
+/// 删除 kill switch 规则 (单独一次 pkexec 授权)
+pub fn disable_kill_switch() -> Result<(), String> {
+    let script = format!(
+        "if nft list table inet {t} >/dev/null 2>&1; then nft delete table inet {t}; fi",
+        t = kill_switch::TABLE
+    );
+    let output = Command::new("pkexec")
+        .args(["/bin/sh", "-c", &script])
+        .output()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "nft failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// 检查 kill switch 规则是否在位；普通用户无权读取规则集时返回 None
+pub fn verify_kill_switch() -> Option<bool> {
+    let output = Command::new("nft")
+        .args(["list", "table", "inet", kill_switch::TABLE])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        return Some(String::from_utf8_lossy(&output.stdout).contains("policy drop"));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("No such file or directory") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// 当前网络标识：/sys/class/net 中处于 up 状态的网卡，以及 NetworkManager 报告的已连接 SSID
+pub fn current_network() -> Option<NetworkIdentity> {
+    let mut interfaces: Vec<String> = fs::read_dir("/sys/class/net")
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != "lo" && name != tun::INTERFACE_NAME)
+        .filter(|name| {
+            fs::read_to_string(format!("/sys/class/net/{}/operstate", name))
+                .map(|s| s.trim() == "up")
+                .unwrap_or(false)
+        })
+        .collect();
+    interfaces.sort();
+
+    // --rescan no：只读缓存结果，避免每次轮询都触发 Wi-Fi 扫描；未安装 NetworkManager 时只按网卡判断
+    let ssids = Command::new("nmcli")
+        .args(["-t", "-f", "ACTIVE,SSID", "device", "wifi", "list", "--rescan", "no"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .filter_map(|line| line.strip_prefix("yes:"))
+                .map(|ssid| ssid.replace("\\:", ":"))
+                .filter(|ssid| !ssid.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(NetworkIdentity { ssids, interfaces })
+}
+
+/// 监听网络变化：rtnetlink 的网卡/地址/默认路由事件与 logind 的 PrepareForSleep 信号，
+/// 事件经 `tx` 发出；两路都无法建立时返回 false
+pub fn watch_network_changes(tx: std::sync::mpsc::Sender<NetworkChange>) -> bool {
+    let netlink = spawn_netlink_listener(tx.clone());
+    let logind = spawn_sleep_listener(tx);
+    netlink || logind
+}
+
+fn spawn_netlink_listener(tx: std::sync::mpsc::Sender<NetworkChange>) -> bool {
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_ROUTE) };
+    if fd < 0 {
+        warn!("netlink socket failed: {}", std::io::Error::last_os_error());
+        return false;
+    }
+
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = (libc::RTMGRP_LINK
+        | libc::RTMGRP_IPV4_IFADDR
+        | libc::RTMGRP_IPV6_IFADDR
+        | libc::RTMGRP_IPV4_ROUTE
+        | libc::RTMGRP_IPV6_ROUTE) as u32;
+    let bound = unsafe {
+        libc::bind(
+            fd,
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if bound < 0 {
+        warn!("netlink bind failed: {}", std::io::Error::last_os_error());
+        unsafe { libc::close(fd) };
+        return false;
+    }
+
+    std::thread::spawn(move || {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            let n = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if n < 0 {
+                let err = std::io::Error::last_os_error();
+                // ENOBUFS：事件过多被内核丢弃，按一次变化处理
+                match err.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    Some(libc::ENOBUFS) => {
+                        if tx.send(NetworkChange::Link).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    _ => {
+                        warn!("netlink recv failed: {}", err);
+                        break;
+                    }
+                }
+            }
+            let changes = parse_netlink_messages(&buf[..n as usize]);
+            if changes.into_iter().any(|change| tx.send(change).is_err()) {
+                break;
+            }
+        }
+        unsafe { libc::close(fd) };
+    });
+    true
+}
+
+/// 解析 rtnetlink 消息；忽略本应用 TUN 网卡自身的变化与非主路由表的路由
+fn parse_netlink_messages(mut data: &[u8]) -> Vec<NetworkChange> {
+    const HEADER_LEN: usize = std::mem::size_of::<libc::nlmsghdr>();
+    let tun_index = std::ffi::CString::new(tun::INTERFACE_NAME)
+        .map(|name| unsafe { libc::if_nametoindex(name.as_ptr()) })
+        .unwrap_or(0);
+    let mut changes = Vec::new();
+
+    while data.len() >= HEADER_LEN {
+        let len = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let kind = u16::from_ne_bytes([data[4], data[5]]);
+        if len < HEADER_LEN || len > data.len() {
+            break;
+        }
+        let body = &data[HEADER_LEN..len];
+
+        // ifinfomsg / ifaddrmsg 的网卡序号均位于偏移 4
+        let index = body.get(4..8).map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]));
+        let is_tun = tun_index != 0 && index == Some(tun_index);
+        match kind {
+            libc::RTM_NEWLINK | libc::RTM_DELLINK if !is_tun => changes.push(NetworkChange::Link),
+            libc::RTM_NEWADDR | libc::RTM_DELADDR if !is_tun => changes.push(NetworkChange::Address),
+            libc::RTM_NEWROUTE | libc::RTM_DELROUTE => {
+                // rtmsg：dst_len 位于偏移 1，table 位于偏移 4；sing-box auto_route 使用独立路由表
+                let default_main = body.get(1) == Some(&0) && body.get(4) == Some(&libc::RT_TABLE_MAIN);
+                if default_main {
+                    changes.push(NetworkChange::DefaultRoute);
+                }
+            }
+            _ => {}
+        }
+
+        // 消息按 4 字节对齐
+        let aligned = (len + 3) & !3;
+        data = data.get(aligned..).unwrap_or_default();
+    }
+    changes
+}
+
+/// 经 gdbus 订阅 logind 的 PrepareForSleep 信号；参数为 false 表示已唤醒
+fn spawn_sleep_listener(tx: std::sync::mpsc::Sender<NetworkChange>) -> bool {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::process::CommandExt;
+
+    let mut command = Command::new("gdbus");
+    command
+        .args([
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    // 应用退出时随之结束，不留下孤儿进程
+    unsafe {
+        command.pre_exec(|| {
+            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+            Ok(())
+        });
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("gdbus unavailable, resume detection disabled: {}", e);
+            return false;
+        }
+    };
+    let Some(stdout) = child.stdout.take() else {
+        return false;
+    };
+
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            if line.contains("PrepareForSleep") && line.contains("(false,)") && tx.send(NetworkChange::Resume).is_err() {
+                break;
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    });
+    true
+}
+
+/// 本应用的 TUN 网卡是否仍存在
+pub fn tun_interface_present() -> bool {
+    Path::new("/sys/class/net").join(tun::INTERFACE_NAME).exists()
+}
+
+/// 默认路由 (ip route)、resolv.conf 中的 nameserver 与 GNOME 代理模式
+pub fn network_snapshot() -> NetworkSnapshot {
+    let default_routes = Command::new("ip")
+        .args(["route", "show", "default"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+        .unwrap_or_default();
+
+    let dns_servers = fs::read_to_string("/etc/resolv.conf")
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|l| l.trim().strip_prefix("nameserver"))
+                .map(|s| s.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let system_proxy = Command::new("gsettings")
+        .args(["get", "org.gnome.system.proxy", "mode"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+
+    NetworkSnapshot { default_routes, dns_servers, system_proxy }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+
+    fn ruleset(ips: &[&str]) -> String {
+        build_kill_switch_ruleset(&KillSwitchRules {
+            allow_ips: ips.iter().map(|ip| ip.parse::<IpAddr>().unwrap()).collect(),
+        })
+    }
+
+    /// output 链中的规则行
+    fn chain_lines(ruleset: &str) -> Vec<String> {
+        ruleset
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| !l.starts_with("chain output"))
+            .skip(1)
+            .take_while(|l| *l != "}")
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn ruleset_replaces_table_atomically() {
+        let text = ruleset(&["203.0.113.7"]);
+        let lines: Vec<&str> = text.lines().collect();
+        // 先声明空表保证 delete 不因表不存在而失败，随后整表重建；三者同属一次 nft -f 事务
+        assert_eq!(lines[0], "table inet tovpn_killswitch");
+        assert_eq!(lines[1], "delete table inet tovpn_killswitch");
+        assert_eq!(lines[2], "table inet tovpn_killswitch {");
+        assert_eq!(text.matches("chain output").count(), 1);
+        assert!(text.ends_with("}\n"));
+    }
+
+    #[test]
+    fn ruleset_drops_by_default_and_accepts_tunnel() {
+        let lines = chain_lines(&ruleset(&["203.0.113.7"]));
+        assert_eq!(
+            lines[..4],
+            [
+                "type filter hook output priority 0; policy drop;",
+                "oifname \"lo\" accept",
+                "oifname \"tovpn0\" accept",
+                "meta mark 0x7476 accept",
+            ]
+        );
+    }
+
+    #[test]
+    fn ruleset_splits_node_ips_by_family() {
+        // 节点、备选节点与上游代理地址在 engage 中合并为同一列表
+        let lines = chain_lines(&ruleset(&[
+            "203.0.113.7",
+            "2001:db8::7",
+            "198.51.100.9",
+            "192.0.2.1",
+        ]));
+        assert!(
+            lines.contains(&"ip daddr { 203.0.113.7, 198.51.100.9, 192.0.2.1 } accept".to_string())
+        );
+        assert!(lines.contains(&"ip6 daddr { 2001:db8::7 } accept".to_string()));
+    }
+
+    #[test]
+    fn ruleset_omits_empty_family_sets() {
+        let lines = chain_lines(&ruleset(&["203.0.113.7"]));
+        // 空集合 `ip6 daddr { }` 会使整个事务失败
+        assert!(!lines.iter().any(|l| l.contains("{  }")));
+        assert_eq!(
+            lines.iter().filter(|l| l.starts_with("ip6 daddr")).count(),
+            1
+        );
+
+        let lines = chain_lines(&ruleset(&["2001:db8::7"]));
+        assert_eq!(
+            lines.iter().filter(|l| l.starts_with("ip daddr")).count(),
+            1
+        );
+        assert!(lines.contains(&"ip6 daddr { 2001:db8::7 } accept".to_string()));
+    }
+
+    #[test]
+    fn ruleset_allows_lan_and_dhcp() {
+        let lines = chain_lines(&ruleset(&["203.0.113.7"]));
+        assert!(lines.contains(&format!(
+            "ip daddr {{ {} }} accept",
+            kill_switch::LAN_V4.join(", ")
+        )));
+        assert!(lines.contains(&format!(
+            "ip6 daddr {{ {} }} accept",
+            kill_switch::LAN_V6.join(", ")
+        )));
+        assert!(lines.contains(&"udp sport 68 udp dport 67 accept".to_string()));
+        assert!(lines.contains(&"udp sport 546 udp dport 547 accept".to_string()));
+        // 链头 + lo/tun/mark + 节点 + 局域网 v4/v6 + DHCP v4/v6，没有其他规则
+        assert_eq!(lines.len(), 9);
+    }
+
+    #[test]
+    fn start_failure_maps_pkexec_codes() {
+        assert_eq!(start_failure(Some(126)), "pkexec: authorization dismissed");
+        assert_eq!(start_failure(Some(127)), "pkexec: not authorized");
+        assert_eq!(
+            start_failure(Some(1)),
+            "sing-box failed to start (exit code 1)"
+        );
+        assert_eq!(start_failure(None), "sing-box failed to start");
+    }
+}
@@ -1,10 +1,8 @@
 //! macOS 平台层 - TUN 模式管理
 //! 版本：v2025-12-22-Final
 
-use super::TunPrecheck;
-use crate::constants::{
-    get_singbox_pid_file, get_tun_lock_file, SINGBOX_API_PORT_SOCKS, SINGBOX_API_PORT_TUN,
-};
+use super::{owned, KillSwitchRules, NetworkChange, NetworkIdentity, NetworkSnapshot, TunPrecheck};
+use crate::constants::{get_singbox_pid_file, get_tun_lock_file, teardown};
 
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -16,8 +14,6 @@ use std::time::{Duration, Instant};
 
 const SYSTEM_BIN_PATH: &str = "/Library/Application Support/ToVPN/sing-box";
 const CMD_SUDO: &str = "/usr/bin/sudo";
-const CMD_PGREP: &str = "/usr/bin/pgrep";
-const CMD_PKILL: &str = "/usr/bin/pkill";
 #[allow(dead_code)]
 const CMD_ROUTE: &str = "/sbin/route";
 
@@ -47,6 +43,7 @@ fn can_sudo_run_singbox_nopasswd() -> bool {
     Path::new(SYSTEM_BIN_PATH).exists() && sudo_ok(&[SYSTEM_BIN_PATH, "version"])
 }
 
+/// 结束监听 `port` 的进程，仅限本应用登记过的 sing-box
 fn kill_process_by_port(port: u16) {
     let port_str = format!(":{}", port);
     if let Ok(output) = Command::new("lsof")
@@ -55,9 +52,11 @@ fn kill_process_by_port(port: u16) {
     {
         let pids = String::from_utf8_lossy(&output.stdout);
         for pid in pids.lines() {
-            let pid = pid.trim();
-            if !pid.is_empty() {
-                let _ = Command::new("kill").args(["-9", pid]).output();
+            let Ok(pid) = pid.trim().parse::<u32>() else {
+                continue;
+            };
+            if owned::is_owned(pid) {
+                let _ = Command::new("kill").args(["-9", &pid.to_string()]).output();
             }
         }
     }
@@ -84,12 +83,9 @@ pub fn detect_default_interface() -> Option<String> {
     None
 }
 
+/// 检查本应用启动的 sing-box 是否在运行
 pub fn is_singbox_running() -> bool {
-    Command::new(CMD_PGREP)
-        .args(["-x", "sing-box"])
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+    owned::any_alive()
 }
 
 pub fn check_singbox_installed() -> bool {
@@ -172,17 +168,12 @@ fn wait_port_free(port: u16, timeout_ms: u64) -> bool {
     true
 }
 
+/// 先 SIGTERM 等待退出，超时后 SIGKILL；再清理仍占用 1080 端口的登记进程
 fn quick_kill_singbox() {
+    if let Err(e) = owned::terminate_all(Duration::from_millis(teardown::STOP_GRACE_MS)) {
+        tracing::warn!("Stop sing-box failed: {}", e);
+    }
     kill_process_by_port(1080);
-    kill_process_by_port(SINGBOX_API_PORT_TUN);
-    kill_process_by_port(SINGBOX_API_PORT_SOCKS);
-
-    let _ = Command::new(CMD_SUDO)
-        .args(["-n", "-k", CMD_PKILL, "-9", "-x", "sing-box"])
-        .output();
-    let _ = Command::new(CMD_PKILL)
-        .args(["-9", "-x", "sing-box"])
-        .output();
 }
 
 pub fn run_singbox_tun_as_root(config_path: &str, log_file: &str) -> Result<(), String> {
@@ -204,6 +195,8 @@ pub fn run_singbox_tun_as_root(config_path: &str, log_file: &str) -> Result<(),
         .map_err(|e| format!("Failed to spawn sing-box: {}", e))?;
 
     let _ = fs::write(get_singbox_pid_file(), child.id().to_string());
+    // sudo 将信号转发给 sing-box 子进程，登记 sudo 本身即可
+    owned::register(child.id(), true);
 
     if let Err(e) = wait_for_singbox_started(&actual_log, 8000) {
         force_cleanup();
@@ -234,10 +227,77 @@ pub fn force_cleanup() {
     quick_kill_singbox();
     let _ = wait_process_stop(1000);
 
-    wait_port_free(SINGBOX_API_PORT_TUN, 500);
-    wait_port_free(SINGBOX_API_PORT_SOCKS, 500);
     wait_port_free(1080, 500);
 
     let _ = fs::remove_file(get_singbox_pid_file());
     let _ = fs::remove_file(get_tun_lock_file());
 }
+
+/// Kill switch 目前仅支持 Linux (nftables)
+pub fn enable_kill_switch(_rules: &KillSwitchRules) -> Result<(), String> {
+    Err("Kill switch is not supported on this platform".to_string())
+}
+
+pub fn disable_kill_switch() -> Result<(), String> {
+    Ok(())
+}
+
+pub fn verify_kill_switch() -> Option<bool> {
+    Some(false)
+}
+
+/// 网络标识检测暂未实现，自动连接只在启动时生效
+pub fn current_network() -> Option<NetworkIdentity> {
+    None
+}
+
+/// 网络变化监听暂未实现，依赖 sing-box 自身的网卡检测与进程 watchdog
+pub fn watch_network_changes(_tx: std::sync::mpsc::Sender<NetworkChange>) -> bool {
+    false
+}
+
+/// utun 网卡由系统分配编号，无法归属到本应用；残留以默认路由变化体现
+pub fn tun_interface_present() -> bool {
+    false
+}
+
+/// 默认路由 (netstat)、scutil 报告的 DNS 与当前网络服务的 SOCKS 代理设置
+pub fn network_snapshot() -> NetworkSnapshot {
+    let stdout = |cmd: &str, args: &[&str]| -> String {
+        Command::new(cmd)
+            .args(args)
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+            .unwrap_or_default()
+    };
+
+    let default_routes = stdout("netstat", &["-rn", "-f", "inet"])
+        .lines()
+        .filter(|l| l.starts_with("default"))
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+
+    let mut dns_servers: Vec<String> = stdout("scutil", &["--dns"])
+        .lines()
+        .filter(|l| l.trim_start().starts_with("nameserver["))
+        .filter_map(|l| l.split(':').nth(1))
+        .map(|s| s.trim().to_string())
+        .collect();
+    dns_servers.sort();
+    dns_servers.dedup();
+
+    let system_proxy = crate::vpn::proxy::get_active_network_service().map(|service| {
+        stdout("networksetup", &["-getsocksfirewallproxy", &service])
+            .lines()
+            .find(|l| l.starts_with("Enabled"))
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    });
+
+    NetworkSnapshot {
+        default_routes,
+        dns_servers,
+        system_proxy,
+    }
+}
@@ -1,5 +1,7 @@
 //! 平台特定实现模块
 
+pub mod owned;
+
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
@@ -23,5 +25,44 @@ pub struct TunPrecheck {
     pub will_prompt: bool,
 }
 
+/// Kill switch 放行规则：除回环、TUN 网卡、局域网与 sing-box 自身标记流量外，
+/// 只允许访问这些地址 (节点与上游代理)
+#[derive(Debug, Clone)]
+pub struct KillSwitchRules {
+    pub allow_ips: Vec<std::net::IpAddr>,
+}
+
+/// 当前网络标识，用于自动连接策略判断受信任网络
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct NetworkIdentity {
+    /// 已连接的 Wi-Fi SSID
+    pub ssids: Vec<String>,
+    /// 处于 up 状态的物理/虚拟网卡（不含回环与本应用的 TUN 网卡）
+    pub interfaces: Vec<String>,
+}
+
+/// 连接前的网络配置快照，断开后据此确认路由、DNS 与系统代理已复原
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct NetworkSnapshot {
+    /// 默认路由 (平台命令的原始输出行)
+    pub default_routes: Vec<String>,
+    pub dns_servers: Vec<String>,
+    /// 系统代理设置；无法读取时为 None
+    pub system_proxy: Option<String>,
+}
+
+/// 系统网络变化通知
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkChange {
+    /// 网卡增删或状态变化
+    Link,
+    /// 网卡地址变化 (DHCP 续租、漫游)
+    Address,
+    /// 主路由表默认路由变化
+    DefaultRoute,
+    /// 系统从休眠中唤醒
+    Resume,
+}
+
 // 确保在所有平台的实现文件中都定义并导出了此函数
 // 在 macos.rs, windows.rs, linux.rs 中应已定义
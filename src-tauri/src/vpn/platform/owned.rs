@@ -0,0 +1,361 @@
+//! 本应用启动的 sing-box 进程登记
+//! 以 pid + 进程启动时间 + 命令行识别，结束进程与清理端口时只针对登记过的进程，
+//! 不影响用户自行运行的 sing-box 或其他服务
+
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::constants::{get_owned_process_file, teardown};
+use crate::vpn::security;
+
+/// 登记的进程
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnedProcess {
+    pub pid: u32,
+    /// 进程启动时间（pid 复用后必然不同）
+    pub start_time: String,
+    /// 登记时的命令行；提权包装 (pkexec/sudo) 执行后会变化，只用于确认仍与 sing-box 相关
+    pub command: String,
+    /// 以 root/管理员身份运行，结束时需要提权
+    pub privileged: bool,
+}
+
+impl OwnedProcess {
+    /// 进程仍存在且身份未变
+    fn is_alive(&self) -> bool {
+        match identity(self.pid) {
+            Some((start_time, command)) => {
+                start_time == self.start_time && command.contains("sing-box")
+            }
+            None => false,
+        }
+    }
+}
+
+fn load() -> Vec<OwnedProcess> {
+    fs::read_to_string(get_owned_process_file())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn store(processes: &[OwnedProcess]) {
+    let path = get_owned_process_file();
+    if processes.is_empty() {
+        let _ = fs::remove_file(path);
+        return;
+    }
+    match serde_json::to_string(processes) {
+        Ok(json) => {
+            // 记录决定提权结束哪些进程，只允许当前用户写入
+            if let Err(e) = security::write_private(&path, json.as_bytes()) {
+                warn!("Failed to persist owned processes: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize owned processes: {}", e),
+    }
+}
+
+/// 登记刚启动的进程；无法读取身份（已退出）时忽略
+pub fn register(pid: u32, privileged: bool) {
+    let Some((start_time, command)) = identity(pid) else {
+        warn!(pid, "Cannot identify spawned process, not tracking");
+        return;
+    };
+    let mut processes = load();
+    processes.retain(|p| p.pid != pid);
+    processes.push(OwnedProcess {
+        pid,
+        start_time,
+        command,
+        privileged,
+    });
+    debug!(pid, privileged, "Tracking owned sing-box");
+    store(&processes);
+}
+
+/// 仍在运行的登记进程；顺带移除已退出或 pid 已被复用的记录
+pub fn alive() -> Vec<OwnedProcess> {
+    let processes = load();
+    let alive: Vec<_> = processes.iter().filter(|p| p.is_alive()).cloned().collect();
+    if alive.len() != processes.len() {
+        store(&alive);
+    }
+    alive
+}
+
+pub fn any_alive() -> bool {
+    !alive().is_empty()
+}
+
+/// 按端口清理时确认监听进程归属
+#[cfg(target_os = "macos")]
+pub fn is_owned(pid: u32) -> bool {
+    alive().iter().any(|p| p.pid == pid)
+}
+
+/// Linux：/proc/<pid>/stat 的 starttime 字段与 cmdline
+#[cfg(target_os = "linux")]
+fn identity(pid: u32) -> Option<(String, String)> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // comm 字段可能含空格，从最后一个 ')' 之后切分；starttime 为第 22 个字段
+    let fields = stat.get(stat.rfind(')')? + 1..)?;
+    // 已退出未回收的僵尸进程视为不存在 (pkexec 启动的 sing-box 是本进程的子进程)
+    if fields.split_whitespace().next()? == "Z" {
+        return None;
+    }
+    let start_time = fields.split_whitespace().nth(19)?.to_string();
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let command = cmdline
+        .split(|b| *b == 0)
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some((start_time, command))
+}
+
+/// macOS：ps 的 lstart 与 command 列
+#[cfg(target_os = "macos")]
+fn identity(pid: u32) -> Option<(String, String)> {
+    let ps = |column: &str| -> Option<String> {
+        let out = Command::new("/bin/ps")
+            .args(["-o", column, "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let value = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        (out.status.success() && !value.is_empty()).then_some(value)
+    };
+    Some((ps("lstart=")?, ps("command=")?))
+}
+
+/// Windows：Win32_Process 的创建时间与映像名（提权进程的命令行对普通用户不可见）
+#[cfg(target_os = "windows")]
+fn identity(pid: u32) -> Option<(String, String)> {
+    let script = format!(
+        "$p = Get-CimInstance Win32_Process -Filter \"ProcessId={}\"; if ($p) {{ $p.CreationDate.ToFileTimeUtc(); $p.Name }}",
+        pid
+    );
+    let out = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let mut lines = stdout.lines().map(str::trim).filter(|l| !l.is_empty());
+    Some((lines.next()?.to_string(), lines.next()?.to_string()))
+}
+
+/// 有序结束登记进程：先请求退出，等待至多 `grace`，仍存活的再强制结束
+///
+/// 返回 (是否动用了强制结束, 强制结束后仍存活的 pid)；信号未能送达 (如用户取消提权) 时返回错误
+pub fn terminate_all(grace: Duration) -> Result<(bool, Vec<u32>), String> {
+    #[cfg(target_os = "linux")]
+    let forced = terminate_privileged(grace)?;
+    #[cfg(not(target_os = "linux"))]
+    let forced = false;
+
+    if alive().is_empty() {
+        return Ok((forced, Vec::new()));
+    }
+    request_exit()?;
+    if wait_gone(grace) {
+        return Ok((forced, Vec::new()));
+    }
+    warn!("sing-box did not exit within {:?}, killing", grace);
+    force_kill()?;
+    wait_gone(Duration::from_millis(teardown::KILL_WAIT_MS));
+    Ok((true, alive().iter().map(|p| p.pid).collect()))
+}
+
+fn wait_gone(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if alive().is_empty() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// 未送达的进程若已自行退出则不算失败
+fn undelivered(failed: Vec<u32>, action: &str) -> Result<(), String> {
+    let still_alive: Vec<u32> = alive()
+        .iter()
+        .map(|p| p.pid)
+        .filter(|pid| failed.contains(pid))
+        .collect();
+    if still_alive.is_empty() {
+        Ok(())
+    } else {
+        warn!(pids = ?still_alive, "Failed to {} sing-box", action);
+        Err(format!("failed to {} sing-box {:?}", action, still_alive))
+    }
+}
+
+fn command_ok(command: &mut Command) -> bool {
+    command
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn request_exit() -> Result<(), String> {
+    signal_all("-TERM")
+}
+
+#[cfg(unix)]
+fn force_kill() -> Result<(), String> {
+    signal_all("-KILL")
+}
+
+/// 不带 /F 的 taskkill 发送关闭请求
+#[cfg(target_os = "windows")]
+fn request_exit() -> Result<(), String> {
+    let failed = alive()
+        .into_iter()
+        .filter(|p| !command_ok(Command::new("taskkill").args(["/T", "/PID", &p.pid.to_string()])))
+        .map(|p| p.pid)
+        .collect();
+    undelivered(failed, "stop")
+}
+
+#[cfg(target_os = "windows")]
+fn force_kill() -> Result<(), String> {
+    kill_all()
+}
+
+/// 向登记进程发送信号 (unix)；提权进程以提权方式发送，任一进程未送达时返回错误
+#[cfg(unix)]
+pub fn signal_all(signal: &str) -> Result<(), String> {
+    let (privileged, plain): (Vec<_>, Vec<_>) = alive().into_iter().partition(|p| p.privileged);
+    let mut failed: Vec<u32> = plain
+        .into_iter()
+        .filter(|p| !command_ok(Command::new("kill").args([signal, &p.pid.to_string()])))
+        .map(|p| p.pid)
+        .collect();
+    if !privileged.is_empty() {
+        let pids: Vec<u32> = privileged.iter().map(|p| p.pid).collect();
+        if let Err(e) = signal_privileged(signal, &pids) {
+            warn!(signal, "Privileged signal failed: {}", e);
+            failed.extend(pids);
+        }
+    }
+    undelivered(failed, &format!("send {} to", signal))
+}
+
+/// 提权执行的有序结束：TERM，等待至多宽限期 (僵尸进程视为已退出)，仍存活的 KILL；
+/// 以退出码 3 表示动用了 KILL
+#[cfg(target_os = "linux")]
+const TERMINATE_SCRIPT: &str = r#"
+ticks="$0"
+kill -TERM "$@" || exit 1
+i=0
+while [ "$i" -lt "$ticks" ]; do
+    running=
+    for pid in "$@"; do
+        state=$(sed 's/.*) //' "/proc/$pid/stat" 2>/dev/null | cut -d' ' -f1)
+        [ -n "$state" ] && [ "$state" != Z ] && running=1
+    done
+    [ -z "$running" ] && exit 0
+    sleep 0.1
+    i=$((i + 1))
+done
+kill -KILL "$@" 2>/dev/null
+exit 3
+"#;
+
+/// Linux：在一次 pkexec 中结束全部提权进程，整个断开过程只弹出一次授权；
+/// 返回是否动用了 KILL
+#[cfg(target_os = "linux")]
+fn terminate_privileged(grace: Duration) -> Result<bool, String> {
+    let pids: Vec<String> = alive()
+        .iter()
+        .filter(|p| p.privileged)
+        .map(|p| p.pid.to_string())
+        .collect();
+    if pids.is_empty() {
+        return Ok(false);
+    }
+    let ticks = (grace.as_millis() / 100).max(1).to_string();
+    let out = Command::new("pkexec")
+        .args(["/bin/sh", "-c", TERMINATE_SCRIPT, &ticks])
+        .args(&pids)
+        .output()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+    match out.status.code() {
+        Some(0) => Ok(false),
+        Some(3) => {
+            warn!("sing-box did not exit within {:?}, killed", grace);
+            wait_gone(Duration::from_millis(teardown::KILL_WAIT_MS));
+            Ok(true)
+        }
+        code => {
+            let err = pkexec_error(code, &out.stderr);
+            warn!("Privileged stop failed: {}", err);
+            Err(err)
+        }
+    }
+}
+
+/// pkexec 约定：126 为用户取消授权，127 为未获授权
+#[cfg(target_os = "linux")]
+fn pkexec_error(code: Option<i32>, stderr: &[u8]) -> String {
+    match code {
+        Some(126) => "pkexec: authorization dismissed".to_string(),
+        Some(127) => "pkexec: not authorized".to_string(),
+        _ => String::from_utf8_lossy(stderr).trim().to_string(),
+    }
+}
+
+/// Linux：TUN 模式的 sing-box 经 pkexec 以 root 运行，普通用户的 kill 只会得到 EPERM，
+/// 同样经 pkexec 发送
+#[cfg(target_os = "linux")]
+fn signal_privileged(signal: &str, pids: &[u32]) -> Result<(), String> {
+    let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+    let out = Command::new("pkexec")
+        .args(["/bin/sh", "-c", r#"kill "$0" "$@""#, signal])
+        .args(&pids)
+        .output()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(pkexec_error(out.status.code(), &out.stderr))
+    }
+}
+
+/// macOS：TUN 模式依赖 sudo 免密规则，信号同样经 sudo -n 发送
+#[cfg(target_os = "macos")]
+fn signal_privileged(signal: &str, pids: &[u32]) -> Result<(), String> {
+    let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+    let out = Command::new("/usr/bin/sudo")
+        .args(["-n", "/bin/kill", signal])
+        .args(&pids)
+        .output()
+        .map_err(|e| format!("Failed to run sudo: {}", e))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).trim().to_string())
+    }
+}
+
+/// 结束登记进程 (Windows)
+#[cfg(target_os = "windows")]
+pub fn kill_all() -> Result<(), String> {
+    let failed = alive()
+        .into_iter()
+        .filter(|p| {
+            !command_ok(Command::new("taskkill").args(["/F", "/T", "/PID", &p.pid.to_string()]))
+        })
+        .map(|p| p.pid)
+        .collect();
+    undelivered(failed, "kill")
+}
@@ -1,34 +1,14 @@
 //! Windows 平台特定实现
-use super::TunPrecheck;
-use crate::constants::{get_singbox_pid_file, SINGBOX_API_PORT_TUN};
+use super::{owned, KillSwitchRules, NetworkChange, NetworkIdentity, NetworkSnapshot, TunPrecheck};
+use crate::constants::{get_singbox_pid_file, teardown};
 use std::fs;
 use std::net::TcpStream;
 use std::process::Command;
 use std::time::{Duration, Instant};
 
+/// 检查本应用启动的 sing-box 是否在运行
 pub fn is_singbox_running() -> bool {
-    if let Ok(pid_str) = fs::read_to_string(get_singbox_pid_file()) {
-        if let Ok(pid) = pid_str.trim().parse::<u32>() {
-            if let Ok(output) = Command::new("tasklist")
-                .args(["/FI", &format!("PID eq {}", pid), "/NH"])
-                .output()
-            {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                if stdout.contains("sing-box") {
-                    return true;
-                }
-            }
-        }
-    }
-
-    Command::new("tasklist")
-        .args(["/FI", "IMAGENAME eq sing-box.exe", "/NH"])
-        .output()
-        .map(|o| {
-            let stdout = String::from_utf8_lossy(&o.stdout);
-            stdout.contains("sing-box")
-        })
-        .unwrap_or(false)
+    owned::any_alive()
 }
 
 pub fn check_singbox_installed() -> bool {
@@ -59,7 +39,7 @@ pub fn run_singbox_tun_as_root(config_path: &str, _log_file: &str) -> Result<(),
 
     let escaped_config = config_path.replace("'", "''").replace("\\", "\\\\");
     let ps_command = format!(
-        "Start-Process -FilePath 'sing-box' -ArgumentList 'run','-c','{}' -Verb RunAs -WindowStyle Hidden",
+        "(Start-Process -FilePath 'sing-box' -ArgumentList 'run','-c','{}' -Verb RunAs -WindowStyle Hidden -PassThru).Id",
         escaped_config
     );
 
@@ -70,6 +50,11 @@ pub fn run_singbox_tun_as_root(config_path: &str, _log_file: &str) -> Result<(),
     match output {
         Ok(o) => {
             if o.status.success() {
+                // -PassThru 输出提权进程的 pid
+                if let Ok(pid) = String::from_utf8_lossy(&o.stdout).trim().parse::<u32>() {
+                    let _ = fs::write(get_singbox_pid_file(), pid.to_string());
+                    owned::register(pid, true);
+                }
                 std::thread::sleep(Duration::from_millis(1500));
                 if is_singbox_running() {
                     Ok(())
@@ -85,20 +70,19 @@ pub fn run_singbox_tun_as_root(config_path: &str, _log_file: &str) -> Result<(),
     }
 }
 
+/// 只结束本应用登记过的 sing-box；先请求关闭，超时后强制结束
 pub fn stop_singbox_tun_as_root() -> Result<(), String> {
-    let _ = Command::new("taskkill")
-        .args(["/F", "/IM", "sing-box.exe"])
-        .output();
+    let (_, leftover) = owned::terminate_all(Duration::from_millis(teardown::STOP_GRACE_MS))?;
     let _ = fs::remove_file(get_singbox_pid_file());
-    std::thread::sleep(Duration::from_millis(500));
-    Ok(())
+    if leftover.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("sing-box still running: {:?}", leftover))
+    }
 }
 
 pub fn force_cleanup() {
     let _ = stop_singbox_tun_as_root();
-    let _ = Command::new("taskkill")
-        .args(["/F", "/T", "/IM", "sing-box.exe"])
-        .output();
 }
 
 pub fn set_system_socks_proxy(enable: bool) {
@@ -153,3 +137,69 @@ pub fn set_system_socks_proxy(enable: bool) {
 pub fn detect_default_interface() -> Option<String> {
     None
 }
+
+/// Kill switch 目前仅支持 Linux (nftables)
+pub fn enable_kill_switch(_rules: &KillSwitchRules) -> Result<(), String> {
+    Err("Kill switch is not supported on this platform".to_string())
+}
+
+pub fn disable_kill_switch() -> Result<(), String> {
+    Ok(())
+}
+
+pub fn verify_kill_switch() -> Option<bool> {
+    Some(false)
+}
+
+/// 网络标识检测暂未实现，自动连接只在启动时生效
+pub fn current_network() -> Option<NetworkIdentity> {
+    None
+}
+
+/// 网络变化监听暂未实现，依赖 sing-box 自身的网卡检测与进程 watchdog
+pub fn watch_network_changes(_tx: std::sync::mpsc::Sender<NetworkChange>) -> bool {
+    false
+}
+
+/// wintun 网卡随 sing-box 进程退出自动移除
+pub fn tun_interface_present() -> bool {
+    false
+}
+
+/// 默认路由 (route print) 与 Internet Settings 中的代理开关；DNS 由 wintun 网卡携带，随网卡移除
+pub fn network_snapshot() -> NetworkSnapshot {
+    let default_routes = Command::new("route")
+        .args(["print", "-4", "0.0.0.0"])
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|l| l.starts_with("0.0.0.0"))
+                .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let system_proxy = Command::new("reg")
+        .args([
+            "query",
+            "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings",
+            "/v",
+            "ProxyEnable",
+        ])
+        .output()
+        .ok()
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .find(|l| l.contains("ProxyEnable"))
+                .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        });
+
+    NetworkSnapshot {
+        default_routes,
+        dns_servers: Vec::new(),
+        system_proxy,
+    }
+}
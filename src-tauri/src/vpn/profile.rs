@@ -0,0 +1,272 @@
+//! 连接配置档案 (Profile) 模块
+//! 将模式、DNS、路由规则和节点偏好打包为命名档案，由后端持久化到应用数据目录
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::error::{CommandResult, ErrorPayload, Result, VpnError};
+
+use super::config::{ConnectConfig, ServerNode, TunOptions};
+use super::connect::connect_with_config;
+use super::security;
+use super::state::VpnState;
+use super::upstream::UpstreamProxy;
+
+const PROFILES_FILE: &str = "profiles.json";
+
+/// 命名连接档案，如 "Office" / "Travel" / "Gaming"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionProfile {
+    pub name: String,
+    pub mode: String,
+    /// 0 = auto
+    pub mtu: u16,
+    pub dns: String,
+    pub tun: TunOptions,
+    /// 空 = 不使用，"auto" = 环境变量探测，其余为代理 URL
+    pub upstream_proxy: Option<String>,
+    pub kill_switch: bool,
+    pub node: Option<ServerNode>,
+}
+
+impl Default for ConnectionProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            mode: "socks".to_string(),
+            mtu: 0,
+            dns: String::new(),
+            tun: TunOptions::default(),
+            upstream_proxy: None,
+            kill_switch: false,
+            node: None,
+        }
+    }
+}
+
+impl ConnectionProfile {
+    /// 由档案构建连接配置；`node` 优先于档案内保存的节点偏好
+    pub fn to_connect_config(&self, node: Option<&ServerNode>) -> Result<(i32, ConnectConfig)> {
+        let node = node.or(self.node.as_ref()).ok_or_else(|| {
+            VpnError::InvalidServer(format!("Profile '{}' has no node selected", self.name))
+        })?;
+
+        let upstream = UpstreamProxy::from_option(self.upstream_proxy.as_deref(), &node.domain)?;
+        let config = ConnectConfig::new(
+            node.domain.clone(),
+            node.port,
+            node.password.clone(),
+            self.mode.clone(),
+            self.mtu,
+            self.dns.clone(),
+        )
+        .with_tun_options(self.tun.clone())
+        .with_upstream_proxy(upstream)
+        .with_kill_switch(self.kill_switch);
+
+        Ok((node.server_id, config))
+    }
+
+    fn validate(&self) -> Result<()> {
+        let name = self.name.trim();
+        if name.is_empty() || name.len() > 64 {
+            return Err(VpnError::Config(
+                "Profile name must be 1-64 characters".to_string(),
+            ));
+        }
+        // 未保存节点时以占位节点校验模式/MTU/DNS/路由等其余字段
+        let placeholder = ServerNode {
+            server_id: 0,
+            domain: "localhost".to_string(),
+            port: 443,
+            password: "-".to_string(),
+        };
+        let (_, config) = self.to_connect_config(self.node.as_ref().or(Some(&placeholder)))?;
+        config.validate()
+    }
+}
+
+/// 档案列表 + 当前激活档案
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileStore {
+    pub profiles: Vec<ConnectionProfile>,
+    pub active: Option<String>,
+    /// 最近一次成功连接使用的节点，自动连接时档案未保存节点则使用它
+    #[serde(default)]
+    pub last_node: Option<ServerNode>,
+}
+
+impl ProfileStore {
+    pub fn load(dir: &Path) -> Self {
+        fs::read_to_string(dir.join(PROFILES_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(PROFILES_FILE);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
+        // 档案中保存了节点密码
+        security::write_private(&path, json.as_bytes())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConnectionProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// 激活档案，未激活时回退到第一个
+    pub fn active_profile(&self) -> Option<&ConnectionProfile> {
+        self.active
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.profiles.first())
+    }
+}
+
+fn profiles_dir(app_handle: &AppHandle) -> CommandResult<std::path::PathBuf> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| VpnError::Io(e.to_string()).into())
+}
+
+#[tauri::command]
+pub fn list_profiles(app_handle: AppHandle) -> CommandResult<ProfileStore> {
+    Ok(ProfileStore::load(&profiles_dir(&app_handle)?))
+}
+
+#[tauri::command]
+pub fn create_profile(
+    app_handle: AppHandle,
+    profile: ConnectionProfile,
+) -> CommandResult<ProfileStore> {
+    let dir = profiles_dir(&app_handle)?;
+    let mut store = ProfileStore::load(&dir);
+
+    profile.validate().map_err(ErrorPayload::from)?;
+    if store.get(&profile.name).is_some() {
+        return Err(VpnError::Config(format!("Profile '{}' already exists", profile.name)).into());
+    }
+
+    info!(name = %profile.name, "Creating profile");
+    store.profiles.push(profile);
+    store.save(&dir).map_err(ErrorPayload::from)?;
+    Ok(store)
+}
+
+#[tauri::command]
+pub fn update_profile(
+    app_handle: AppHandle,
+    name: String,
+    profile: ConnectionProfile,
+) -> CommandResult<ProfileStore> {
+    let dir = profiles_dir(&app_handle)?;
+    let mut store = ProfileStore::load(&dir);
+
+    profile.validate().map_err(ErrorPayload::from)?;
+    if profile.name != name && store.get(&profile.name).is_some() {
+        return Err(VpnError::Config(format!("Profile '{}' already exists", profile.name)).into());
+    }
+
+    let slot = store
+        .profiles
+        .iter_mut()
+        .find(|p| p.name == name)
+        .ok_or_else(|| VpnError::Config(format!("Profile '{}' not found", name)))?;
+
+    // 重命名时同步激活状态
+    if store.active.as_deref() == Some(name.as_str()) {
+        store.active = Some(profile.name.clone());
+    }
+    *slot = profile;
+
+    store.save(&dir).map_err(ErrorPayload::from)?;
+    Ok(store)
+}
+
+#[tauri::command]
+pub fn delete_profile(app_handle: AppHandle, name: String) -> CommandResult<ProfileStore> {
+    let dir = profiles_dir(&app_handle)?;
+    let mut store = ProfileStore::load(&dir);
+
+    let before = store.profiles.len();
+    store.profiles.retain(|p| p.name != name);
+    if store.profiles.len() == before {
+        return Err(VpnError::Config(format!("Profile '{}' not found", name)).into());
+    }
+    if store.active.as_deref() == Some(name.as_str()) {
+        store.active = None;
+    }
+
+    store.save(&dir).map_err(ErrorPayload::from)?;
+    Ok(store)
+}
+
+#[tauri::command]
+pub fn activate_profile(app_handle: AppHandle, name: String) -> CommandResult<ProfileStore> {
+    let dir = profiles_dir(&app_handle)?;
+    let mut store = ProfileStore::load(&dir);
+
+    if store.get(&name).is_none() {
+        return Err(VpnError::Config(format!("Profile '{}' not found", name)).into());
+    }
+    store.active = Some(name);
+
+    store.save(&dir).map_err(ErrorPayload::from)?;
+    Ok(store)
+}
+
+/// 按档案连接；`name` 缺省时使用激活档案，`node` 缺省时使用档案的节点偏好
+#[tauri::command]
+pub async fn connect_profile(
+    app_handle: AppHandle,
+    state: tauri::State<'_, VpnState>,
+    name: Option<String>,
+    node: Option<ServerNode>,
+    alternates: Option<Vec<ServerNode>>,
+) -> CommandResult<String> {
+    let store = ProfileStore::load(&profiles_dir(&app_handle)?);
+    let profile = match name.as_deref() {
+        Some(name) => store.get(name),
+        None => store.active_profile(),
+    }
+    .ok_or_else(|| VpnError::Config("Profile not found".to_string()))?;
+
+    let (server_id, config) = profile
+        .to_connect_config(node.as_ref())
+        .map_err(ErrorPayload::from)?;
+    let config = config.with_alternates(alternates.unwrap_or_default());
+
+    let profile_name = profile.name.clone();
+    let used_node = node.or_else(|| profile.node.clone());
+
+    info!(profile = %profile_name, server_id, "Connecting with profile");
+    let result = connect_with_config(&app_handle, &state, server_id, config).await;
+    if result.is_ok() {
+        remember_last_used(&app_handle, profile_name, used_node);
+    }
+    result
+}
+
+/// 记录最近使用的档案与节点，供自动连接使用
+fn remember_last_used(app_handle: &AppHandle, name: String, node: Option<ServerNode>) {
+    let Ok(dir) = profiles_dir(app_handle) else {
+        return;
+    };
+    let mut store = ProfileStore::load(&dir);
+    store.active = Some(name);
+    if node.is_some() {
+        store.last_node = node;
+    }
+    if let Err(e) = store.save(&dir) {
+        warn!("Failed to record last used profile: {}", e);
+    }
+}
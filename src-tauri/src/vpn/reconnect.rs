@@ -0,0 +1,413 @@
+//! 自动重连模块
+//! sing-box 意外退出后由后端按指数退避 + 抖动重试，窗口被挂起或关闭到托盘时同样生效
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::constants::reconnect;
+use crate::error::{CommandResult, ErrorPayload, Result, VpnError};
+
+use super::connect::{cleanup_before_connect, cleanup_connection, do_connect, finish_connect};
+use super::crashloop;
+use super::monitor::{begin_attempt, emit_log, transition_status};
+use super::state::{VpnState, VpnStatusEnum};
+use super::timing::{ConnectPhase, ConnectTimer};
+
+const POLICY_FILE: &str = "reconnect.json";
+
+/// 重连策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub enabled: bool,
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_attempts: reconnect::MAX_ATTEMPTS,
+            base_delay_ms: reconnect::BASE_DELAY_MS,
+            max_delay_ms: reconnect::MAX_DELAY_MS,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn load(dir: &Path) -> Self {
+        fs::read_to_string(dir.join(POLICY_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
+        fs::write(dir.join(POLICY_FILE), json)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.max_attempts == 0 || self.max_attempts > 100 {
+            return Err(VpnError::Config(
+                "Reconnect attempts must be 1-100".to_string(),
+            ));
+        }
+        if self.base_delay_ms == 0 || self.base_delay_ms > self.max_delay_ms {
+            return Err(VpnError::Config(
+                "Reconnect base delay must be positive and not exceed max delay".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 第 `attempt` 次 (从 1 开始) 重试前的等待：指数退避封顶后取 [d/2, d] 区间的随机值，
+    /// 避免多个客户端同时断线后集中重连
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(20));
+        let capped = exp.min(self.max_delay_ms);
+        let jittered = capped / 2 + rand::thread_rng().gen_range(0..=capped / 2);
+        Duration::from_millis(jittered)
+    }
+}
+
+/// `vpn-reconnect` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconnectEvent {
+    /// scheduled / attempting / failed / succeeded / exhausted / cancelled / crash_loop
+    pub phase: &'static str,
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub error: Option<String>,
+}
+
+fn emit_reconnect(
+    app: &AppHandle,
+    phase: &'static str,
+    attempt: u32,
+    policy: &ReconnectPolicy,
+    delay_ms: u64,
+    error: Option<String>,
+) {
+    let _ = app.emit(
+        "vpn-reconnect",
+        ReconnectEvent {
+            phase,
+            attempt,
+            max_attempts: policy.max_attempts,
+            delay_ms,
+            error,
+        },
+    );
+}
+
+fn policy_dir(app: &AppHandle) -> Option<std::path::PathBuf> {
+    app.path().app_data_dir().ok()
+}
+
+fn load_policy(app: &AppHandle) -> ReconnectPolicy {
+    policy_dir(app)
+        .map(|dir| ReconnectPolicy::load(&dir))
+        .unwrap_or_default()
+}
+
+/// 自动重连开关（界面设置同步到重连策略）
+pub fn is_enabled(app: &AppHandle) -> bool {
+    load_policy(app).enabled
+}
+
+/// 处理 sing-box 意外退出（调用方已完成清理）
+///
+/// 策略允许时迁移到 Reconnecting 并在后台重试，返回 true；
+/// 返回 false 时由调用方按失败处理；节点被标记为故障时不重连
+pub fn handle_unexpected_exit(app: &AppHandle, reason: &str) -> bool {
+    let policy = load_policy(app);
+    if !policy.enabled {
+        return false;
+    }
+
+    let state = app.state::<VpnState>();
+    let Some((server_id, config)) = state.get_last_connect() else {
+        return false;
+    };
+    if crashloop::failing_node(&state, server_id).is_some() {
+        return false;
+    }
+    // 用户断开/取消时经由令牌中止退避等待和进行中的尝试
+    let Ok(cancel) = begin_attempt(app, &state, VpnStatusEnum::Reconnecting, reason) else {
+        return false;
+    };
+
+    info!(reason = %reason, max_attempts = policy.max_attempts, "Scheduling auto-reconnect");
+    emit_log(
+        app,
+        "warn",
+        &format!("Connection lost ({}), reconnecting...", reason),
+    );
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_reconnect(app, policy, server_id, config, cancel, false).await;
+    });
+    true
+}
+
+/// 网络切换或休眠唤醒后立即重建连接：停止仍在运行的 sing-box，首次尝试不等待；
+/// 同样受自动重连开关控制，重试次数与退避沿用重连策略
+pub async fn reconnect_now(app: &AppHandle, reason: &str) -> bool {
+    let policy = load_policy(app);
+    if !policy.enabled {
+        return false;
+    }
+
+    let state = app.state::<VpnState>();
+    let Some((server_id, config)) = state.get_last_connect() else {
+        return false;
+    };
+    if crashloop::failing_node(&state, server_id).is_some() {
+        return false;
+    }
+    let Ok(cancel) = begin_attempt(app, &state, VpnStatusEnum::Reconnecting, reason) else {
+        return false;
+    };
+
+    info!(reason = %reason, "Re-establishing tunnel");
+    emit_log(app, "info", &format!("Re-establishing tunnel ({})", reason));
+    cleanup_connection(app, &state).await;
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_reconnect(app, policy, server_id, config, cancel, true).await;
+    });
+    true
+}
+
+async fn run_reconnect(
+    app: AppHandle,
+    policy: ReconnectPolicy,
+    server_id: i32,
+    config: super::config::ConnectConfig,
+    cancel: CancellationToken,
+    immediate: bool,
+) {
+    let state = app.state::<VpnState>();
+    let mut last_error = String::new();
+
+    for attempt in 1..=policy.max_attempts {
+        let delay = if immediate && attempt == 1 {
+            Duration::ZERO
+        } else {
+            policy.delay_for(attempt)
+        };
+        emit_reconnect(
+            &app,
+            "scheduled",
+            attempt,
+            &policy,
+            delay.as_millis() as u64,
+            None,
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancel.cancelled() => {}
+        }
+
+        // 等待期间用户断开
+        if cancel.is_cancelled() || state.get_status() != VpnStatusEnum::Reconnecting {
+            info!("Auto-reconnect cancelled");
+            emit_reconnect(&app, "cancelled", attempt, &policy, 0, None);
+            return;
+        }
+
+        info!(attempt, "Reconnect attempt");
+        emit_reconnect(&app, "attempting", attempt, &policy, 0, None);
+
+        let mut timer = ConnectTimer::new(&app, &config.mode);
+        timer.phase(ConnectPhase::Cleanup);
+        cleanup_before_connect(&state).await;
+        state.set_server_id(Some(server_id));
+
+        let result = do_connect(&app, &state, &config, &cancel, &mut timer).await;
+
+        // 连接过程中用户断开：丢弃本次结果
+        if cancel.is_cancelled() || state.get_status() != VpnStatusEnum::Reconnecting {
+            timer.finish(&state, "cancelled", None);
+            if result.is_ok() {
+                cleanup_connection(&app, &state).await;
+            }
+            emit_reconnect(&app, "cancelled", attempt, &policy, 0, None);
+            return;
+        }
+
+        match result {
+            Ok(()) => {
+                timer.finish(&state, "connected", None);
+                let reason = format!("reconnected after {} attempt(s)", attempt);
+                if let Err(e) = finish_connect(&app, &state, server_id, &config, &reason) {
+                    warn!("Reconnect finalize failed: {}", e);
+                    cleanup_connection(&app, &state).await;
+                    return;
+                }
+                emit_log(&app, "info", "VPN reconnected");
+                emit_reconnect(&app, "succeeded", attempt, &policy, 0, None);
+                return;
+            }
+            Err(e) => {
+                warn!(attempt, "Reconnect attempt failed: {}", e);
+                timer.finish(&state, "failed", Some(e.user_message()));
+                cleanup_connection(&app, &state).await;
+                last_error = e.to_string();
+                emit_reconnect(&app, "failed", attempt, &policy, 0, Some(e.user_message()));
+                // 启动失败同样计入；判定为崩溃循环时不再继续重试
+                if crashloop::record_exit(&app, &state, server_id, &config.mode, &e, &last_error) {
+                    let reason = format!("crash loop: {}", last_error);
+                    let _ = transition_status(&app, &state, VpnStatusEnum::Failed, &reason);
+                    emit_log(&app, "error", "Node keeps failing, auto-reconnect stopped");
+                    emit_reconnect(&app, "crash_loop", attempt, &policy, 0, Some(last_error));
+                    return;
+                }
+            }
+        }
+    }
+
+    let reason = format!(
+        "reconnect gave up after {} attempts: {}",
+        policy.max_attempts, last_error
+    );
+    let _ = transition_status(&app, &state, VpnStatusEnum::Failed, &reason);
+    emit_log(&app, "error", "Auto-reconnect failed, giving up");
+    emit_reconnect(
+        &app,
+        "exhausted",
+        policy.max_attempts,
+        &policy,
+        0,
+        Some(last_error),
+    );
+}
+
+#[tauri::command]
+pub fn get_reconnect_policy(app_handle: AppHandle) -> CommandResult<ReconnectPolicy> {
+    let dir = policy_dir(&app_handle)
+        .ok_or_else(|| VpnError::Io("App data dir unavailable".to_string()))?;
+    Ok(ReconnectPolicy::load(&dir))
+}
+
+#[tauri::command]
+pub fn set_reconnect_policy(
+    app_handle: AppHandle,
+    policy: ReconnectPolicy,
+) -> CommandResult<ReconnectPolicy> {
+    let dir = policy_dir(&app_handle)
+        .ok_or_else(|| VpnError::Io("App data dir unavailable".to_string()))?;
+    policy.validate().map_err(ErrorPayload::from)?;
+    policy.save(&dir).map_err(ErrorPayload::from)?;
+    info!(enabled = policy.enabled, "Reconnect policy updated");
+    Ok(policy)
+}
+
+/// 只切换开关，保留已保存的重试次数与退避参数
+#[tauri::command]
+pub fn set_reconnect_enabled(
+    app_handle: AppHandle,
+    enabled: bool,
+) -> CommandResult<ReconnectPolicy> {
+    let dir = policy_dir(&app_handle)
+        .ok_or_else(|| VpnError::Io("App data dir unavailable".to_string()))?;
+    let mut policy = ReconnectPolicy::load(&dir);
+    if policy.enabled != enabled {
+        policy.enabled = enabled;
+        policy.save(&dir).map_err(ErrorPayload::from)?;
+        info!(enabled, "Reconnect policy updated");
+    }
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(base_delay_ms: u64, max_delay_ms: u64) -> ReconnectPolicy {
+        ReconnectPolicy {
+            enabled: true,
+            max_attempts: 10,
+            base_delay_ms,
+            max_delay_ms,
+        }
+    }
+
+    /// 多次取样，返回 (最小值, 最大值) 毫秒
+    fn sample(policy: &ReconnectPolicy, attempt: u32) -> (u64, u64) {
+        (0..200)
+            .map(|_| policy.delay_for(attempt).as_millis() as u64)
+            .fold((u64::MAX, 0), |(lo, hi), d| (lo.min(d), hi.max(d)))
+    }
+
+    #[test]
+    fn delay_grows_exponentially_within_jitter_bounds() {
+        let policy = policy(1000, 60_000);
+        for (attempt, full) in [(1, 1000), (2, 2000), (3, 4000), (4, 8000)] {
+            let (lo, hi) = sample(&policy, attempt);
+            assert!(lo >= full / 2, "attempt {}: {} < {}", attempt, lo, full / 2);
+            assert!(hi <= full, "attempt {}: {} > {}", attempt, hi, full);
+            // 抖动确实生效，而不是固定值
+            assert!(hi > lo, "attempt {}: no jitter", attempt);
+        }
+    }
+
+    #[test]
+    fn delay_is_capped_at_max() {
+        let policy = policy(1000, 5000);
+        for attempt in [4, 5, 10, 64, u32::MAX] {
+            let (lo, hi) = sample(&policy, attempt);
+            assert!(
+                lo >= 2500 && hi <= 5000,
+                "attempt {}: {}..{}",
+                attempt,
+                lo,
+                hi
+            );
+        }
+    }
+
+    #[test]
+    fn first_attempt_uses_base_delay() {
+        let policy = policy(1, 1);
+        // attempt 0 按第 1 次处理
+        assert!(policy.delay_for(0) <= Duration::from_millis(1));
+        assert!(policy.delay_for(1) <= Duration::from_millis(1));
+    }
+
+    #[test]
+    fn validate_bounds() {
+        assert!(ReconnectPolicy::default().validate().is_ok());
+        assert!(policy(1000, 500).validate().is_err());
+        assert!(policy(0, 500).validate().is_err());
+        let mut p = policy(1000, 5000);
+        p.max_attempts = 0;
+        assert!(p.validate().is_err());
+    }
+
+    #[test]
+    fn partial_policy_file_keeps_defaults() {
+        let p: ReconnectPolicy = serde_json::from_str(r#"{"enabled":true}"#).unwrap();
+        assert!(p.enabled);
+        assert_eq!(p.max_attempts, reconnect::MAX_ATTEMPTS);
+        assert_eq!(p.max_delay_ms, reconnect::MAX_DELAY_MS);
+    }
+}
@@ -1,7 +1,11 @@
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{Result, VpnError};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -35,14 +39,177 @@ pub fn verify_config(config_path: &Path, sig_path: &Path) -> bool {
     stored_sig == expected_sig
 }
 
-/// 设置严格的文件权限 (Unix: 600, Windows: 仅当前用户)
-pub fn set_secure_permissions(path: &Path) {
+/// 设置严格的文件权限 (Unix: 600)；无法设置时返回错误
+pub fn set_secure_permissions(path: &Path) -> Result<()> {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(path).unwrap().permissions();
-        perms.set_mode(0o600); // 仅所有者可读写
-        fs::set_permissions(path, perms).ok();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?; // 仅所有者可读写
+    }
+    Ok(())
+}
+
+/// 确保目录存在且仅当前用户可访问 (Unix: 700)
+///
+/// 不存在时以 700 创建；已存在时必须是当前用户所有的真实目录，权限过宽则收紧。
+/// 共享的 /tmp 下由其他用户预先创建或替换为符号链接的同名目录会被拒绝
+pub fn ensure_private_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        let meta = fs::symlink_metadata(dir)?;
+        if !meta.is_dir() {
+            return Err(VpnError::Io(format!(
+                "{} is not a directory",
+                dir.display()
+            )));
+        }
+        if meta.uid() != unsafe { libc::getuid() } {
+            return Err(VpnError::Io(format!(
+                "{} is owned by another user",
+                dir.display()
+            )));
+        }
+        if meta.mode() & 0o077 != 0 {
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+/// 写入仅当前用户可读写的文件 (Unix: 600)
+///
+/// 在同一私有目录下以 create_new 新建临时文件，权限在创建时即为 600，写完后重命名覆盖目标：
+/// 不沿用他人预先创建的文件，读者也不会读到半写的内容
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(VpnError::Io(format!("Invalid path {}", path.display())));
+    };
+    ensure_private_dir(dir)?;
+    let tmp = dir.join(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let result = options
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    Ok(result?)
+}
+
+/// 为配置文件写入签名 (`<config>.sig`)、收紧权限并校验，sing-box 启动前调用
+pub fn sign_config(config_path: &Path) -> Result<()> {
+    let signature = sign_content(&fs::read_to_string(config_path)?);
+    let sig_path = config_path.with_extension("json.sig");
+    fs::write(&sig_path, signature)?;
+    set_secure_permissions(config_path)?;
+    set_secure_permissions(&sig_path)?;
+
+    if !verify_config(config_path, &sig_path) {
+        return Err(VpnError::Config("Security check failed".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tovpn-security-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::symlink_metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn write_private_creates_owner_only_file() {
+        let dir = scratch_dir("create").join("nested");
+        let path = dir.join("session.json");
+        write_private(&path, b"{\"secret\":1}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"secret\":1}");
+        #[cfg(unix)]
+        {
+            assert_eq!(mode(&dir), 0o700);
+            assert_eq!(mode(&path), 0o600);
+        }
+        // 不留下临时文件
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_private_replaces_planted_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch_dir("planted");
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = dir.join("session.json");
+        fs::write(&path, "planted").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+
+        write_private(&path, b"fresh").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fresh");
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&dir), 0o700);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_dir_rejects_symlink() {
+        let base = scratch_dir("symlink");
+        let target = base.join("target");
+        fs::create_dir_all(&target).unwrap();
+        let link = base.join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(ensure_private_dir(&link).is_err());
+        assert!(write_private(&link.join("session.json"), b"x").is_err());
+        assert!(!target.join("session.json").exists());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn missing_file_permissions_is_an_error() {
+        let path = scratch_dir("missing").join("absent.json");
+        #[cfg(unix)]
+        assert!(set_secure_permissions(&path).is_err());
+        #[cfg(not(unix))]
+        assert!(set_secure_permissions(&path).is_ok());
     }
 }
-                                                                             
\ No newline at end of file
@@ -0,0 +1,196 @@
+//! 会话持久化模块
+//! 连接建立后写入会话元数据；界面进程崩溃重启时若 sing-box 仍健康则直接接管，不中断隧道。
+//! 只接管 TUN 会话：SOCKS 模式的 sidecar 输出经管道交给界面进程，管道随界面崩溃关闭后
+//! sing-box 下一次写日志即因 SIGPIPE 退出
+
+use std::fs;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+use crate::constants::{get_session_file, get_singbox_pid_file};
+
+use super::clash_api::ClashApi;
+use super::config::ConnectConfig;
+use super::kill_switch::KillSwitchStatus;
+use super::monitor::{emit_log, start_monitor, start_process_watchdog, transition_status};
+use super::platform;
+use super::security;
+use super::state::{VpnState, VpnStatusEnum};
+
+/// 持久化的会话元数据（含节点密码，文件权限收紧为仅当前用户可读）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub pid: Option<u32>,
+    pub mode: String,
+    pub server_id: i32,
+    pub clash_api: ClashApi,
+    /// 连接建立时间 (秒)
+    pub started_at: u64,
+    pub config: ConnectConfig,
+    pub session_nodes: Vec<i32>,
+}
+
+/// 启动时对上次会话的判定
+pub enum StartupSession {
+    /// sing-box 仍在运行且本会话的 Clash API 凭据有效，直接接管
+    Reattach(SessionRecord),
+    /// 进程已退出或无法接管 (SOCKS 模式)，按常规清理；kill switch 规则可能残留
+    Stale(SessionRecord),
+    None,
+}
+
+/// 根据当前状态写入会话文件；未连接时不写
+pub fn save(state: &VpnState) {
+    let (Some(clash_api), Some((server_id, config))) =
+        (state.get_clash_api(), state.get_last_connect())
+    else {
+        return;
+    };
+    let mode = state.get_current_mode();
+    let pid = if mode == "tun" {
+        fs::read_to_string(get_singbox_pid_file())
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+    } else {
+        state.child_pid()
+    };
+
+    let record = SessionRecord {
+        pid,
+        mode,
+        server_id,
+        clash_api,
+        started_at: state.get_connected_at(),
+        config,
+        session_nodes: state.get_session_nodes(),
+    };
+    let path = get_session_file();
+    match serde_json::to_string(&record) {
+        Ok(json) => {
+            // 记录中含节点密码与 Clash API secret
+            if let Err(e) = security::write_private(&path, json.as_bytes()) {
+                warn!("Failed to persist session: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize session: {}", e),
+    }
+}
+
+/// 会话结束（断开、失败或退出应用）时删除会话文件
+pub fn clear() {
+    let _ = fs::remove_file(get_session_file());
+}
+
+fn load() -> Option<SessionRecord> {
+    let content = fs::read_to_string(get_session_file()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 启动阶段（尚无异步运行时）检查上次会话
+pub fn inspect() -> StartupSession {
+    let Some(record) = load() else {
+        return StartupSession::None;
+    };
+    if record.mode != "tun" {
+        info!(pid = ?record.pid, "Previous SOCKS session cannot be reattached");
+        clear();
+        return StartupSession::Stale(record);
+    }
+    if record.is_healthy() {
+        info!(pid = ?record.pid, mode = %record.mode, "Found running session");
+        StartupSession::Reattach(record)
+    } else {
+        warn!(pid = ?record.pid, "Previous session is stale");
+        clear();
+        StartupSession::Stale(record)
+    }
+}
+
+impl SessionRecord {
+    /// 进程存活且 Clash API 以本会话的 secret 应答，才视为本应用拥有的健康会话
+    fn is_healthy(&self) -> bool {
+        if let Some(pid) = self.pid {
+            if !pid_alive(pid) {
+                return false;
+            }
+        }
+        let Ok(client) = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(2))
+            .build()
+        else {
+            return false;
+        };
+        client
+            .get(self.clash_api.url("/version"))
+            .bearer_auth(&self.clash_api.secret)
+            .send()
+            .map(|r| r.status().is_success())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(unix)]
+fn pid_alive(pid: u32) -> bool {
+    // root 进程对普通用户返回 EPERM，同样说明进程存在
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn pid_alive(_pid: u32) -> bool {
+    // Windows 下以 Clash API 应答为准
+    true
+}
+
+/// setup 阶段恢复状态：接管运行中的会话，或标记残留的 kill switch
+pub fn restore(app_handle: &AppHandle, startup: StartupSession, state: &VpnState) {
+    match startup {
+        StartupSession::Reattach(record) => reattach(app_handle, state, record),
+        StartupSession::Stale(record) => {
+            // 规则只在用户断开时撤销；标记为已安装，下一次断开/连接时清除
+            mark_kill_switch(state, &record);
+        }
+        StartupSession::None => {}
+    }
+}
+
+/// 上次会话启用了 kill switch 时，规则在崩溃后仍然存在
+fn mark_kill_switch(state: &VpnState, record: &SessionRecord) {
+    if record.config.kill_switch && record.mode == "tun" {
+        state.set_kill_switch(KillSwitchStatus {
+            supported: true,
+            engaged: true,
+            verified: platform::verify_kill_switch(),
+            ..Default::default()
+        });
+    }
+}
+
+fn reattach(app_handle: &AppHandle, state: &VpnState, record: SessionRecord) {
+    mark_kill_switch(state, &record);
+    state.set_server_id(Some(record.server_id));
+    state.set_current_mode(&record.mode);
+    state.set_connected_at(record.started_at);
+    state.set_clash_api(Some(record.clash_api));
+    state.set_last_connect(record.server_id, record.config.clone());
+    state.set_session_nodes(record.session_nodes);
+
+    let reason = "reattached to running session";
+    if transition_status(app_handle, state, VpnStatusEnum::Connecting, reason).is_err()
+        || transition_status(app_handle, state, VpnStatusEnum::Connected, reason).is_err()
+    {
+        return;
+    }
+
+    // 重新接管的进程没有 stdout 通道，依赖 watchdog 发现退出
+    start_monitor(app_handle.clone(), state);
+    start_process_watchdog(app_handle.clone(), state);
+    info!(
+        server_id = record.server_id,
+        "Reattached to running sing-box"
+    );
+    emit_log(app_handle, "info", "Restored running VPN session");
+}
@@ -0,0 +1,310 @@
+//! sing-box 启动失败诊断
+//! 从 stderr 与 TUN 日志文件中识别常见失败原因，附加到 `vpn-connection-error` 事件
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::constants::get_cache_dir;
+use crate::error::VpnError;
+
+use super::split_level;
+
+/// 日志尾部读取上限
+const LOG_TAIL_BYTES: u64 = 64 * 1024;
+
+/// 失败类型；声明顺序即优先级，多行命中时取最靠前的（越具体越靠前）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    ClockSkew,
+    Tls,
+    Auth,
+    UdpBlocked,
+    Dns,
+    PortInUse,
+    Permission,
+    BinaryMissing,
+    Config,
+}
+
+/// 匹配规则（均为小写）；`*` 分隔的片段须按顺序出现在同一行
+const RULES: &[(FailureKind, &[&str])] = &[
+    (
+        FailureKind::ClockSkew,
+        &[
+            "x509: certificate has expired or is not yet valid",
+            "clock skew",
+            "time skew",
+        ],
+    ),
+    (
+        FailureKind::Tls,
+        &["tls: ", "x509: ", "crypto_error", "handshake failure"],
+    ),
+    (
+        FailureKind::Auth,
+        &["authentication failed", "auth failed", "unauthorized"],
+    ),
+    (
+        FailureKind::UdpBlocked,
+        &[
+            "timeout: no recent network activity",
+            "handshake did not complete in time",
+        ],
+    ),
+    (
+        FailureKind::Dns,
+        &[
+            "lookup *: no such host",
+            "lookup *: server misbehaving",
+            "lookup *: i/o timeout",
+            "dns: exchange failed",
+        ],
+    ),
+    (
+        FailureKind::PortInUse,
+        &[
+            "address already in use",
+            "only one usage of each socket address",
+        ],
+    ),
+    (
+        FailureKind::Permission,
+        &[
+            "operation not permitted",
+            "permission denied",
+            "access is denied",
+            "not authorized",
+            "authorization dismissed",
+            "canceled by the user",
+        ],
+    ),
+    (
+        FailureKind::BinaryMissing,
+        &["executable file not found", "command not found"],
+    ),
+    (
+        FailureKind::Config,
+        &[
+            "decode config",
+            "parse config",
+            "json: unknown field",
+            "json: cannot unmarshal",
+            "initialize inbound",
+            "initialize outbound",
+            "create service",
+        ],
+    ),
+];
+
+/// 诊断结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnosis {
+    pub kind: FailureKind,
+    /// 面向用户的原因说明
+    pub hint: &'static str,
+    /// 命中的原始日志行
+    pub evidence: String,
+}
+
+impl Diagnosis {
+    fn new(kind: FailureKind, evidence: &str) -> Self {
+        let hint = match kind {
+            FailureKind::ClockSkew => "系统时间与服务器偏差过大，请校准系统时间",
+            FailureKind::Tls => "TLS 握手失败，节点证书或 SNI 配置可能有误",
+            FailureKind::Auth => "节点拒绝认证，密码可能已变更或订阅已过期",
+            FailureKind::UdpBlocked => "QUIC 握手超时，当前网络可能封锁 UDP",
+            FailureKind::Dns => "节点域名解析失败，请检查网络或 DNS 设置",
+            FailureKind::PortInUse => "本地端口被占用，请关闭占用端口的程序",
+            FailureKind::Permission => "权限不足或未完成管理员授权",
+            FailureKind::BinaryMissing => "未找到 sing-box 可执行文件",
+            FailureKind::Config => "sing-box 配置无效，请尝试重置设置",
+        };
+        Self {
+            kind,
+            hint,
+            evidence: evidence.trim().to_string(),
+        }
+    }
+
+    /// 转换为对应的错误类型，原始日志行作为技术细节
+    pub fn to_error(&self) -> VpnError {
+        let detail = self.evidence.clone();
+        match self.kind {
+            FailureKind::ClockSkew | FailureKind::Tls => VpnError::TlsFailure(detail),
+            FailureKind::Auth => VpnError::AuthRejected(detail),
+            FailureKind::UdpBlocked => VpnError::UdpBlocked(detail),
+            FailureKind::Dns => VpnError::Network(detail),
+            FailureKind::PortInUse => VpnError::PortInUse(detail),
+            FailureKind::Permission => VpnError::PrivilegeDenied(detail),
+            FailureKind::BinaryMissing => VpnError::BinaryMissing(detail),
+            FailureKind::Config => VpnError::Config(detail),
+        }
+    }
+}
+
+fn matches_needle(line: &str, needle: &str) -> bool {
+    let mut rest = line;
+    for part in needle.split('*') {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// 诊断单行输出；sing-box 日志只看 ERROR 及以上级别，
+/// INFO/DEBUG 里例行出现的证书加载、DNS 规则查询等字样不参与判断
+pub fn classify_line(line: &str) -> Option<Diagnosis> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    // pkexec 等外部命令的输出没有级别标记，照常判断
+    if let Some((level, _)) = split_level(trimmed) {
+        if !matches!(level, "ERROR" | "FATAL" | "PANIC") {
+            return None;
+        }
+    }
+    let lower = trimmed.to_lowercase();
+    RULES
+        .iter()
+        .find(|(_, needles)| needles.iter().any(|n| matches_needle(&lower, n)))
+        .map(|(kind, _)| Diagnosis::new(*kind, trimmed))
+}
+
+/// 诊断多行输出，返回优先级最高的结果
+pub fn classify_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Diagnosis> {
+    lines
+        .into_iter()
+        .filter_map(classify_line)
+        .min_by_key(|d| d.kind)
+}
+
+pub fn classify(text: &str) -> Option<Diagnosis> {
+    classify_lines(text.lines())
+}
+
+/// TUN 模式下 sing-box 输出写入的日志文件
+pub fn tun_log_path() -> PathBuf {
+    get_cache_dir().join("tovpn-tun.log")
+}
+
+/// 诊断 TUN 日志文件尾部
+pub fn diagnose_tun_log() -> Option<Diagnosis> {
+    classify(&read_log_tail(&tun_log_path())?)
+}
+
+fn read_log_tail(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))
+        .ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_singbox_failures() {
+        let cases: &[(&str, FailureKind)] = &[
+            (
+                "+0800 2024-05-01 10:00:00 ERROR [3921 0ms] connection: open outbound connection: tls: failed to verify certificate: x509: certificate has expired or is not yet valid: current time 2020-01-01T00:00:00Z is before 2024-01-01T00:00:00Z",
+                FailureKind::ClockSkew,
+            ),
+            (
+                "ERROR[0003] connection: open outbound connection: tls: failed to verify certificate: x509: certificate is valid for a.example.com, not b.example.com",
+                FailureKind::Tls,
+            ),
+            (
+                "ERROR[0012] outbound/hysteria2[proxy]: CRYPTO_ERROR 0x128 (remote): tls: handshake failure",
+                FailureKind::Tls,
+            ),
+            (
+                "ERROR[0005] connection: open outbound connection: authentication failed, status code: 404",
+                FailureKind::Auth,
+            ),
+            (
+                "ERROR[0010] connection: open outbound connection: timeout: no recent network activity",
+                FailureKind::UdpBlocked,
+            ),
+            (
+                "ERROR[0001] connection: open outbound connection: lookup node.example.com: no such host",
+                FailureKind::Dns,
+            ),
+            (
+                "ERROR[0001] outbound/hysteria2[proxy]: lookup node.example.com on 127.0.0.53:53: server misbehaving",
+                FailureKind::Dns,
+            ),
+            (
+                "FATAL[0000] start service: start inbound/mixed[mixed-in]: listen tcp 127.0.0.1:1080: bind: address already in use",
+                FailureKind::PortInUse,
+            ),
+            (
+                "FATAL[0000] start service: initialize inbound/tun[tun-in]: configure tun interface: operation not permitted",
+                FailureKind::Permission,
+            ),
+            (
+                "Error executing command as another user: Not authorized",
+                FailureKind::Permission,
+            ),
+            ("pkexec: authorization dismissed", FailureKind::Permission),
+            ("sh: 1: sing-box: command not found", FailureKind::BinaryMissing),
+            (
+                "FATAL[0000] decode config at /tmp/config.json: outbounds[0]: json: unknown field \"foo\"",
+                FailureKind::Config,
+            ),
+        ];
+        for (line, kind) in cases {
+            let diagnosis = classify_line(line).unwrap_or_else(|| panic!("unclassified: {}", line));
+            assert_eq!(diagnosis.kind, *kind, "{}", line);
+            assert_eq!(diagnosis.evidence, line.trim());
+        }
+    }
+
+    #[test]
+    fn ignores_routine_lines() {
+        let lines = [
+            "INFO[0000] inbound/tun[tun-in]: started at tun0",
+            "INFO[0000] loaded certificate from /etc/ssl/certs/ca-certificates.crt",
+            "DEBUG[0001] dns: lookup domain www.google.com",
+            "+0800 2024-05-01 10:00:00 DEBUG [3921 0ms] dns: exchanged www.google.com NOERROR 60",
+            "WARN[0002] dns: exchange failed for www.google.com: context canceled",
+            "ERROR[0007] router: rule-set geoip-cn: lookup rule failed: context canceled",
+            "",
+        ];
+        for line in lines {
+            assert!(classify_line(line).is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn most_specific_line_wins() {
+        let log = "INFO[0000] loaded certificate
+FATAL[0000] start service: initialize outbound/hysteria2[proxy]: something
+ERROR[0001] connection: open outbound connection: tls: handshake failure";
+        assert_eq!(classify(log).map(|d| d.kind), Some(FailureKind::Tls));
+        assert!(classify("INFO[0000] sing-box started").is_none());
+    }
+
+    #[test]
+    fn needle_parts_in_order() {
+        assert!(matches_needle(
+            "lookup a.com: no such host",
+            "lookup *: no such host"
+        ));
+        assert!(!matches_needle(
+            "no such host: lookup a.com",
+            "lookup *: no such host"
+        ));
+        assert!(matches_needle("tls: bad", "tls: "));
+    }
+}
@@ -1,20 +1,24 @@
 //! sing-box 配置生成模块入口
 //! 提供公共工具函数和分发逻辑
 
+pub mod diagnose;
 pub mod socks;
 pub mod tun;
 
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::{
     fs,
     net::{IpAddr, ToSocketAddrs},
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 use tracing::info;
 
 use crate::constants;
-use crate::error::Result;
+use crate::error::{Result, VpnError};
+use crate::vpn::clash_api::ClashApi;
 use crate::vpn::config::ConnectConfig;
+use crate::vpn::upstream::UpstreamProxy;
 
 // 嵌入资源文件
 static GEOSITE_CN_SRS: &[u8] = include_bytes!(concat!(
@@ -33,7 +37,7 @@ pub struct RuleSetPaths {
 }
 
 /// 统一入口函数
-pub fn generate_config(config: &ConnectConfig, cache_path: &Path) -> Result<Value> {
+pub fn generate_config(config: &ConnectConfig, cache_path: &Path, api: &ClashApi) -> Result<Value> {
     info!(">>> generate_config (Split Module Mode) <<<");
 
     // 确保规则集存在 (公共逻辑)
@@ -41,12 +45,51 @@ pub fn generate_config(config: &ConnectConfig, cache_path: &Path) -> Result<Valu
     let ruleset_paths = ensure_local_rulesets(&base_dir)?;
 
     if config.mode == "tun" {
-        tun::generate(config, cache_path, ruleset_paths)
+        tun::generate(config, cache_path, ruleset_paths, api)
     } else {
-        socks::generate(config, cache_path, ruleset_paths)
+        socks::generate(config, cache_path, ruleset_paths, api)
     }
 }
 
+/// 在 `dir` 下生成并写入当前模式的配置文件，返回其路径；缓存数据库与规则集同在该目录
+pub fn write_config(dir: &Path, config: &ConnectConfig, api: &ClashApi) -> Result<PathBuf> {
+    let (config_filename, cache_filename) = if config.mode == "tun" {
+        ("config-tun.json", "cache-tun.db")
+    } else {
+        ("config-socks.json", "cache-socks.db")
+    };
+    fs::create_dir_all(dir)?;
+
+    let content = generate_config(config, &dir.join(cache_filename), api)?;
+    let config_json = serde_json::to_string_pretty(&content)
+        .map_err(|e| VpnError::Config(format!("Serialize failed: {}", e)))?;
+
+    let mut config_path = dir.join(config_filename);
+    if fs::write(&config_path, &config_json).is_err() {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        config_path = dir.join(format!("config-{}-{}.json", config.mode, ts));
+        fs::write(&config_path, &config_json)?;
+    }
+    Ok(config_path)
+}
+
+/// sing-box 可执行文件：优先随应用打包的 sidecar (与当前程序同目录)，否则取 PATH 中的 sing-box
+pub fn sidecar_path() -> PathBuf {
+    let name = if cfg!(windows) {
+        "sing-box.exe"
+    } else {
+        "sing-box"
+    };
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
 /// 确保本地规则集文件存在
 pub fn ensure_local_rulesets(base_dir: &Path) -> Result<RuleSetPaths> {
     let rules_dir = base_dir.join("rulesets");
@@ -113,26 +156,136 @@ pub fn pick_remote_dns_address(choice: &str) -> String {
     }
 }
 
+/// 节点出站 tag：0 为主节点，其余依次对应 `ConnectConfig::alternates`
+pub fn node_tag(index: usize) -> String {
+    format!("node-{}", index)
+}
+
+/// 节点出站 helper
+///
+/// 每个节点生成一个 hysteria2 出站，再由 tag 为 "proxy" 的 selector 统一引用，
+/// 运行中可通过 Clash API 切换 selector 而无需重启 sing-box。
+/// 返回 (出站列表, 所有节点 IP)，节点 IP 需直连避免环路
+pub fn node_outbounds(config: &ConnectConfig) -> (Vec<Value>, Vec<IpAddr>) {
+    let primary = (
+        config.server_host.as_str(),
+        config.server_port,
+        config.password.as_str(),
+    );
+    let alternates = config
+        .alternates
+        .iter()
+        .map(|n| (n.domain.as_str(), n.port, n.password.as_str()));
+
+    let mut outbounds = Vec::new();
+    let mut node_ips = Vec::new();
+    for (index, (host, port, password)) in std::iter::once(primary).chain(alternates).enumerate() {
+        let ips = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => resolve_ipv4(host, port),
+        };
+        let server = ips
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| host.to_string());
+
+        let mut outbound = json!({
+            "type": "hysteria2",
+            "tag": node_tag(index),
+            "server": server,
+            "server_port": port,
+            "password": password,
+            "up_mbps": 200,
+            "down_mbps": 500,
+            "tls": {
+                "enabled": true,
+                "alpn": ["h3"],
+                "insecure": true,
+                "server_name": host
+            }
+        });
+        // 上游代理：节点出站经由 HTTP/SOCKS 代理拨号
+        if config.upstream.is_some() {
+            outbound["detour"] = json!("upstream");
+        }
+
+        outbounds.push(outbound);
+        node_ips.extend(ips);
+    }
+
+    let tags: Vec<String> = (0..outbounds.len()).map(node_tag).collect();
+    outbounds.push(json!({
+        "type": "selector",
+        "tag": "proxy",
+        "outbounds": tags,
+        "default": node_tag(0),
+        // 切换后中断旧节点上的连接，由应用重新建立到新节点
+        "interrupt_exist_connections": true
+    }));
+
+    (outbounds, node_ips)
+}
+
+/// 上游代理出站 helper
+///
+/// 代理地址预先解析为 IP，避免 sing-box 通过走代理的 remote-dns 解析代理自身形成环路
+pub fn upstream_outbound(proxy: &UpstreamProxy) -> Value {
+    let server = match proxy.host.parse::<IpAddr>() {
+        Ok(ip) => ip.to_string(),
+        Err(_) => resolve_ipv4(&proxy.host, proxy.port)
+            .first()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| proxy.host.clone()),
+    };
+
+    // 只有 SOCKS5 能承载 hysteria2 的 UDP，HTTP 代理已在校验阶段拒绝
+    let mut outbound = json!({ "type": "socks", "tag": "upstream", "version": "5", "server": server, "server_port": proxy.port });
+    if let Some(username) = &proxy.username {
+        outbound["username"] = json!(username);
+    }
+    if let Some(password) = &proxy.password {
+        outbound["password"] = json!(password);
+    }
+    outbound
+}
+
+const LOG_LEVELS: &[&str] = &["PANIC", "FATAL", "ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+
+/// 定位日志级别标记，返回 (级别, 从级别开始的内容)；兼容 `timestamp: true` 的时间戳前缀
+pub fn split_level(line: &str) -> Option<(&'static str, &str)> {
+    let starts = std::iter::once(0).chain(line.match_indices(' ').map(|(i, _)| i + 1));
+    for start in starts {
+        let rest = &line[start..];
+        for level in LOG_LEVELS {
+            if let Some(after) = rest.strip_prefix(level) {
+                if after.is_empty() || after.starts_with('[') || after.starts_with(' ') {
+                    return Some((level, rest));
+                }
+            }
+        }
+    }
+    None
+}
+
 /// 日志解析 helper
 pub fn parse_log_level(line: &str) -> (&str, String) {
     let trimmed = line.trim();
     if trimmed.is_empty() {
         return ("", String::new());
     }
-    let (level, message) = if trimmed.starts_with("FATAL") {
-        ("error", extract_message(trimmed, "FATAL"))
-    } else if trimmed.starts_with("ERROR") {
-        ("error", extract_message(trimmed, "ERROR"))
-    } else if trimmed.starts_with("WARN") {
-        ("warn", extract_message(trimmed, "WARN"))
-    } else if trimmed.starts_with("INFO") {
-        ("info", extract_message(trimmed, "INFO"))
-    } else if trimmed.starts_with("DEBUG") {
-        ("debug", extract_message(trimmed, "DEBUG"))
-    } else {
-        ("info", trimmed.to_string())
-    };
-    (level, message)
+    match split_level(trimmed) {
+        Some((prefix, rest)) => {
+            let level = match prefix {
+                "PANIC" | "FATAL" | "ERROR" => "error",
+                "WARN" => "warn",
+                "INFO" => "info",
+                _ => "debug",
+            };
+            (level, extract_message(rest, prefix))
+        }
+        None => ("info", trimmed.to_string()),
+    }
 }
 
 fn extract_message(line: &str, prefix: &str) -> String {
@@ -146,6 +299,9 @@ fn extract_message(line: &str, prefix: &str) -> String {
 }
 
 pub fn is_fatal_error(line: &str) -> bool {
-    let u = line.trim().to_uppercase();
-    u.starts_with("FATAL") || u.contains("PANIC") || u.contains("bind: address already in use")
+    let trimmed = line.trim();
+    let lower = trimmed.to_lowercase();
+    matches!(split_level(trimmed), Some(("FATAL" | "PANIC", _)))
+        || lower.starts_with("panic:")
+        || lower.contains("address already in use")
 }
@@ -1,29 +1,25 @@
 //! SOCKS 模式配置模块
 
 use serde_json::{json, Value};
-use std::net::IpAddr;
 use std::path::Path;
 use tracing::info;
 
-use super::{pick_remote_dns_address, resolve_ipv4, RuleSetPaths};
-use crate::constants::{self, DEFAULT_SOCKS_PORT, SINGBOX_API_PORT_SOCKS};
+use super::{node_outbounds, pick_remote_dns_address, upstream_outbound, RuleSetPaths};
+use crate::constants::{self, DEFAULT_SOCKS_PORT};
 use crate::error::Result;
+use crate::vpn::clash_api::ClashApi;
 use crate::vpn::config::ConnectConfig;
 
-pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths) -> Result<Value> {
+pub fn generate(
+    config: &ConnectConfig,
+    cache_path: &Path,
+    ruleset: RuleSetPaths,
+    api: &ClashApi,
+) -> Result<Value> {
     info!(">>> Generating SOCKS config <<<");
 
-    // 1. 解析 IP
-    let server_ips: Vec<IpAddr> = match config.server_host.parse::<IpAddr>() {
-        Ok(ip) => vec![ip],
-        Err(_) => resolve_ipv4(&config.server_host, config.server_port),
-    };
-
-    let hysteria_server = server_ips
-        .iter()
-        .find(|ip| ip.is_ipv4())
-        .map(|ip| ip.to_string())
-        .unwrap_or_else(|| config.server_host.clone());
+    // 1. 节点出站 (selector + 各节点) 与节点 IP
+    let (node_obs, server_ips) = node_outbounds(config);
 
     // 2. Inbounds (SOCKS 特有)
     let inbounds = json!([{
@@ -81,21 +77,7 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
     route_rules.push(json!({ "ip_is_private": true, "outbound": "direct" }));
 
     // 5. Outbounds (复刻原文件逻辑)
-    let proxy_ob = json!({
-        "type": "hysteria2",
-        "tag": "proxy",
-        "server": hysteria_server,
-        "server_port": config.server_port,
-        "password": config.password,
-        "up_mbps": 200,
-        "down_mbps": 500,
-        "tls": {
-            "enabled": true,
-            "alpn": ["h3"],
-            "insecure": true,
-            "server_name": &config.server_host
-        }
-    });
+    let upstream_ob = config.upstream.as_ref().map(upstream_outbound);
 
     let direct_ob = json!({ "type": "direct", "tag": "direct" });
 
@@ -109,19 +91,23 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
         "rules": route_rules
     });
 
+    let mut outbounds = node_obs;
+    outbounds.extend([direct_ob, json!({ "type": "block", "tag": "block" })]);
+    outbounds.extend(upstream_ob);
+
     // 6. 组装 (SOCKS 端口)
     Ok(json!({
         "log": { "level": "info", "timestamp": true },
         "experimental": {
             "clash_api": {
-                "external_controller": format!("127.0.0.1:{}", SINGBOX_API_PORT_SOCKS),
-                "secret": ""
+                "external_controller": api.controller(),
+                "secret": api.secret
             },
             "cache_file": { "enabled": true, "path": cache_path }
         },
         "dns": dns_config,
         "inbounds": inbounds,
-        "outbounds": [ proxy_ob, direct_ob, { "type": "block", "tag": "block" } ],
+        "outbounds": outbounds,
         "route": route_config
     }))
 }
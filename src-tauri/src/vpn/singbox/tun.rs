@@ -1,14 +1,19 @@
 //! TUN 模式配置模块
-use super::{pick_remote_dns_address, resolve_ipv4, RuleSetPaths};
-use crate::constants::{self, tun, MTU_MAX, SINGBOX_API_PORT_TUN};
+use super::{node_outbounds, pick_remote_dns_address, upstream_outbound, RuleSetPaths};
+use crate::constants::{self, tun, MTU_MAX};
 use crate::error::Result;
+use crate::vpn::clash_api::ClashApi;
 use crate::vpn::config::ConnectConfig;
 use serde_json::{json, Value};
-use std::net::IpAddr;
 use std::path::Path;
 use tracing::info;
 
-pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths) -> Result<Value> {
+pub fn generate(
+    config: &ConnectConfig,
+    cache_path: &Path,
+    ruleset: RuleSetPaths,
+    api: &ClashApi,
+) -> Result<Value> {
     info!(">>> Generating TUN config (Dual Stack) <<<");
 
     // 1. 基础参数
@@ -18,27 +23,19 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
         MTU_MAX
     };
 
-    // 2. 解析 IP
-    let server_ips: Vec<IpAddr> = match config.server_host.parse::<IpAddr>() {
-        Ok(ip) => vec![ip],
-        Err(_) => resolve_ipv4(&config.server_host, config.server_port),
-    };
-    let hysteria_server = server_ips
-        .iter()
-        .find(|ip| ip.is_ipv4())
-        .map(|ip| ip.to_string())
-        .unwrap_or_else(|| config.server_host.clone());
+    // 2. 节点出站 (selector + 各节点) 与节点 IP
+    let (node_obs, server_ips) = node_outbounds(config);
 
     // 3. Inbounds (TUN 特有)
-    let inbounds = json!([{
+    let mut tun_inbound = json!({
         "type": "tun",
         "tag": "tun-in",
         // FIX 1: 添加 IPv6 CIDR，让 TUN 网卡具备接收 IPv6 能力
         "address": [tun::IPV4_ADDRESS,tun::IPV6_ADDRESS],
         "mtu": mtu,
         "auto_route": true,
-        "strict_route": true,
-        "stack": "gvisor",//gvisor
+        "strict_route": config.tun.strict_route,
+        "stack": config.tun.stack,
         "sniff": true,
         "sniff_override_destination": true,
         "platform": {
@@ -48,7 +45,18 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
                 "server_port": 0
             }
         }
-    }]);
+    });
+    if !config.tun.route_address.is_empty() {
+        tun_inbound["route_address"] = json!(config.tun.route_address);
+    }
+    if !config.tun.route_exclude_address.is_empty() {
+        tun_inbound["route_exclude_address"] = json!(config.tun.route_exclude_address);
+    }
+    // 固定网卡名，供 Linux kill switch 按网卡放行
+    if cfg!(target_os = "linux") {
+        tun_inbound["interface_name"] = json!(tun::INTERFACE_NAME);
+    }
+    let inbounds = json!([tun_inbound]);
 
     // 4. DNS
     let local_dns_addr = constants::dns::ALIYUN_UDP;
@@ -100,24 +108,19 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
     route_rules.push(json!({ "ip_is_private": true, "outbound": "direct" }));
 
     // 6. Outbounds
-    let proxy_ob = json!({
-        "type": "hysteria2",
-        "tag": "proxy",
-        "server": hysteria_server,
-        "server_port": config.server_port,
-        "password": config.password,
-        "up_mbps": 200,
-        "down_mbps": 500,
-        "tcp_fast_open": true,
-        "tls": {
-            "enabled": true,
-            "alpn": ["h3"],
-            "insecure": true,
-            "server_name": &config.server_host
-        }
-    });
+    let node_obs: Vec<Value> = node_obs
+        .into_iter()
+        .map(|mut ob| {
+            if ob["type"] == "hysteria2" {
+                ob["tcp_fast_open"] = json!(true);
+            }
+            ob
+        })
+        .collect();
+    let upstream_ob = config.upstream.as_ref().map(upstream_outbound);
 
     // let direct_ob = json!({ "type": "direct", "tag": "direct" });
+
     // 修改 outbounds 部分
     let direct_ob = json!({
         "type": "direct",
@@ -125,7 +128,7 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
         "bind_interface": "en0" // 这是一个难点：不同机器网卡名不同
     });
 
-    let route_config = json!({
+    let mut route_config = json!({
         "auto_detect_interface": true,
 
         "final": "proxy",
@@ -136,24 +139,28 @@ pub fn generate(config: &ConnectConfig, cache_path: &Path, ruleset: RuleSetPaths
         ],
         "rules": route_rules
     });
+    // kill switch 放行带此标记的 sing-box 自身出站 (直连分流、本地 DNS)
+    if config.kill_switch && cfg!(target_os = "linux") {
+        route_config["default_mark"] = json!(constants::kill_switch::BYPASS_MARK);
+    }
+
+    let mut outbounds = node_obs;
+    outbounds.extend([direct_ob, json!({ "type": "block", "tag": "block" })]);
+    outbounds.extend(upstream_ob);
 
     // 7. 组装
     Ok(json!({
         "log": { "level": "info", "timestamp": true },
         "experimental": {
             "clash_api": {
-                "external_controller": format!("127.0.0.1:{}", SINGBOX_API_PORT_TUN),
-                "secret": ""
+                "external_controller": api.controller(),
+                "secret": api.secret
             },
             "cache_file": { "enabled": true, "path": cache_path }
         },
         "dns": dns_config,
         "inbounds": inbounds,
-        "outbounds": [
-            proxy_ob,
-            direct_ob,
-            { "type": "block", "tag": "block" }
-        ],
+        "outbounds": outbounds,
         "route": route_config
     }))
 }
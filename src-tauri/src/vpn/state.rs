@@ -1,18 +1,41 @@
 //! VPN 状态管理模块
 
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::Serialize;
 use tauri_plugin_shell::process::CommandChild;
-
+use tokio_util::sync::CancellationToken;
+
+use super::clash_api::ClashApi;
+use super::config::ConnectConfig;
+use super::crashloop::CrashTracker;
+use super::history::ActiveSession;
+use super::kill_switch::KillSwitchStatus;
+use super::mtu::MtuMeasurement;
+use super::platform::NetworkSnapshot;
+use super::teardown::CleanupReport;
+use super::timing::ConnectTiming;
+use crate::error::{Result, VpnError};
+
+/// 保留的状态迁移记录条数
+const TRANSITION_HISTORY_LIMIT: usize = 32;
+
+/// 连接状态机
+///
+/// Disconnected → Connecting → Connected → Reconnecting / Disconnecting → Disconnected，
+/// 任何进行中的状态都可能进入 Failed (含断开时清理失败)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum VpnStatusEnum {
     Disconnected,
     Connecting,
     Connected,
+    Reconnecting,
     Disconnecting,
+    Failed,
 }
 
 impl VpnStatusEnum {
@@ -21,9 +44,44 @@ impl VpnStatusEnum {
             VpnStatusEnum::Disconnected => "disconnected",
             VpnStatusEnum::Connecting => "connecting",
             VpnStatusEnum::Connected => "connected",
+            VpnStatusEnum::Reconnecting => "reconnecting",
             VpnStatusEnum::Disconnecting => "disconnecting",
+            VpnStatusEnum::Failed => "failed",
         }
     }
+
+    /// 合法的状态迁移
+    pub fn can_transition_to(&self, next: VpnStatusEnum) -> bool {
+        use VpnStatusEnum::*;
+        matches!(
+            (self, next),
+            (Disconnected, Connecting)
+                | (Connecting, Connected)
+                | (Connecting, Disconnecting)
+                | (Connecting, Failed)
+                | (Connected, Reconnecting)
+                | (Connected, Disconnecting)
+                | (Connected, Failed)
+                | (Reconnecting, Connected)
+                | (Reconnecting, Disconnecting)
+                | (Reconnecting, Failed)
+                | (Disconnecting, Disconnected)
+                | (Disconnecting, Failed)
+                | (Failed, Connecting)
+                | (Failed, Reconnecting)
+                | (Failed, Disconnected)
+        )
+    }
+}
+
+/// 一次状态迁移记录（同时作为 `vpn-state-transition` 事件负载）
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusTransition {
+    pub from: VpnStatusEnum,
+    pub to: VpnStatusEnum,
+    pub reason: String,
+    /// 毫秒时间戳
+    pub timestamp: u64,
 }
 
 #[derive(Serialize, Clone)]
@@ -32,6 +90,10 @@ pub struct VpnStatusResult {
     pub server_id: Option<i32>,
     pub connected_at: Option<u64>,
     pub mode: String,
+    /// 最近一次状态迁移的原因
+    pub reason: Option<String>,
+    /// MTU 为 auto 时探测得到的 TUN MTU
+    pub mtu: Option<u16>,
 }
 
 #[derive(Serialize, Clone, Default)]
@@ -49,19 +111,52 @@ pub struct LatencyStats {
 
 pub struct VpnState {
     pub child: Mutex<Option<CommandChild>>,
-    pub status: Mutex<VpnStatusEnum>,
+    status: Mutex<VpnStatusEnum>,
+    transitions: Mutex<VecDeque<StatusTransition>>,
+    /// 会话代号：每次启动 sing-box 递增，用于识别过期进程的退出事件
+    session: AtomicU64,
     pub server_id: Mutex<Option<i32>>,
     pub connected_at: AtomicU64,
     pub current_mode: Mutex<String>,
 
+    /// 进行中的连接/重连尝试的取消令牌
+    pub connect_cancel: Mutex<Option<CancellationToken>>,
+
+    /// 当前会话的 Clash API 凭据（每次连接重新生成）
+    pub clash_api: Mutex<Option<ClashApi>>,
+
+    /// 本次会话的 Path-MTU 探测结果
+    pub mtu: Mutex<Option<MtuMeasurement>>,
+
+    /// 最近一次成功连接的节点与配置，供自动重连复用（`reset` 不清除）
+    pub last_connect: Mutex<Option<(i32, ConnectConfig)>>,
+
+    /// 运行中 sing-box 的节点出站顺序 (下标即 `node-{i}` tag)，用于热切换
+    pub session_nodes: Mutex<Vec<i32>>,
+
+    /// kill switch 规则状态，跨越重连保持，仅用户断开时清除
+    pub kill_switch: Mutex<KillSwitchStatus>,
+
+    /// 最近一次连接尝试的阶段耗时（`reset` 不清除）
+    pub connect_timing: Mutex<Option<ConnectTiming>>,
+
+    /// 进行中会话的流量/延迟统计，结束时写入会话历史（`reset` 不清除）
+    pub active_session: Mutex<Option<ActiveSession>>,
+
+    /// 本次连接前的网络配置快照，断开后据此校验清理结果
+    pub network_baseline: Mutex<Option<NetworkSnapshot>>,
+
+    /// 最近一次断开清理的报告
+    pub cleanup_report: Mutex<Option<CleanupReport>>,
+
+    /// sing-box 退出记录与故障节点标记（`reset` 不清除）
+    pub crash_tracker: Mutex<CrashTracker>,
+
     /// 仅用于 traffic/latency monitor 线程
     pub monitor_running: Arc<AtomicBool>,
 
     /// 仅用于进程看门狗 watchdog 线程（与 monitor 解耦）
     pub watchdog_running: Arc<AtomicBool>,
-
-    /// 用户主动断开标志（用于避免“正常断开”被当成崩溃）
-    pub user_disconnect: Arc<AtomicBool>,
 }
 
 impl Default for VpnState {
@@ -75,12 +170,24 @@ impl VpnState {
         Self {
             child: Mutex::new(None),
             status: Mutex::new(VpnStatusEnum::Disconnected),
+            transitions: Mutex::new(VecDeque::new()),
+            session: AtomicU64::new(0),
             server_id: Mutex::new(None),
             connected_at: AtomicU64::new(0),
             current_mode: Mutex::new(String::new()),
+            connect_cancel: Mutex::new(None),
+            clash_api: Mutex::new(None),
+            mtu: Mutex::new(None),
+            last_connect: Mutex::new(None),
+            session_nodes: Mutex::new(Vec::new()),
+            kill_switch: Mutex::new(KillSwitchStatus::default()),
+            connect_timing: Mutex::new(None),
+            active_session: Mutex::new(None),
+            network_baseline: Mutex::new(None),
+            cleanup_report: Mutex::new(None),
+            crash_tracker: Mutex::new(CrashTracker::default()),
             monitor_running: Arc::new(AtomicBool::new(false)),
             watchdog_running: Arc::new(AtomicBool::new(false)),
-            user_disconnect: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -91,9 +198,94 @@ impl VpnState {
             .unwrap_or(VpnStatusEnum::Disconnected)
     }
 
-    pub fn set_status(&self, new_status: VpnStatusEnum) {
-        if let Ok(mut status) = self.status.lock() {
-            *status = new_status;
+    /// 执行状态迁移，非法迁移返回错误且不修改状态
+    pub fn transition(&self, to: VpnStatusEnum, reason: &str) -> Result<StatusTransition> {
+        let mut status = self.lock_status()?;
+        self.apply_transition(&mut status, to, reason)
+    }
+
+    /// 执行状态迁移并开始一次连接尝试，返回其取消令牌（旧令牌一并取消）。
+    /// 令牌在状态锁内登记：迁移被拒绝时不影响进行中的尝试，迁移后到达的取消请求也总能取到令牌
+    pub fn begin_connect_attempt(
+        &self,
+        to: VpnStatusEnum,
+        reason: &str,
+    ) -> Result<(StatusTransition, CancellationToken)> {
+        let mut status = self.lock_status()?;
+        let record = self.apply_transition(&mut status, to, reason)?;
+        let token = CancellationToken::new();
+        if let Ok(mut current) = self.connect_cancel.lock() {
+            if let Some(old) = current.replace(token.clone()) {
+                old.cancel();
+            }
+        }
+        Ok((record, token))
+    }
+
+    fn lock_status(&self) -> Result<std::sync::MutexGuard<'_, VpnStatusEnum>> {
+        self.status
+            .lock()
+            .map_err(|_| VpnError::Io("VPN state lock poisoned".to_string()))
+    }
+
+    fn apply_transition(
+        &self,
+        status: &mut VpnStatusEnum,
+        to: VpnStatusEnum,
+        reason: &str,
+    ) -> Result<StatusTransition> {
+        let from = *status;
+        if !from.can_transition_to(to) {
+            return Err(VpnError::InvalidTransition(format!(
+                "{} -> {}",
+                from.as_str(),
+                to.as_str()
+            )));
+        }
+        *status = to;
+
+        let record = StatusTransition {
+            from,
+            to,
+            reason: reason.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        if let Ok(mut history) = self.transitions.lock() {
+            if history.len() >= TRANSITION_HISTORY_LIMIT {
+                history.pop_front();
+            }
+            history.push_back(record.clone());
+        }
+        Ok(record)
+    }
+
+    pub fn get_transitions(&self) -> Vec<StatusTransition> {
+        self.transitions
+            .lock()
+            .map(|h| h.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn last_transition(&self) -> Option<StatusTransition> {
+        self.transitions.lock().ok().and_then(|h| h.back().cloned())
+    }
+
+    /// 开始新的 sing-box 会话，返回会话代号
+    pub fn begin_session(&self) -> u64 {
+        self.session.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn current_session(&self) -> u64 {
+        self.session.load(Ordering::SeqCst)
+    }
+
+    /// 取消进行中的连接/重连尝试
+    pub fn cancel_connect_attempt(&self) {
+        if let Some(token) = self.connect_cancel.lock().ok().and_then(|mut c| c.take()) {
+            token.cancel();
         }
     }
 
@@ -109,17 +301,8 @@ impl VpnState {
         self.connected_at.store(timestamp, Ordering::SeqCst);
     }
 
-    pub fn set_user_disconnect(&self, value: bool) {
-        self.user_disconnect.store(value, Ordering::SeqCst);
-    }
-
-    pub fn get_user_disconnect_flag(&self) -> Arc<AtomicBool> {
-        Arc::clone(&self.user_disconnect)
-    }
-
+    /// 清理会话数据；状态本身只能通过 `transition` 修改
     pub fn reset(&self) {
-        self.set_status(VpnStatusEnum::Disconnected);
-
         if let Ok(mut server_id) = self.server_id.lock() {
             *server_id = None;
         }
@@ -130,15 +313,17 @@ impl VpnState {
             *mode = String::new();
         }
 
-        // 注意：不要在这里重置 user_disconnect。
-        // SOCKS 模式后台日志线程用它来判断 Terminated 是否为用户主动断开。
+        self.set_clash_api(None);
+        self.set_mtu(None);
+        self.set_session_nodes(Vec::new());
+
         self.monitor_running.store(false, Ordering::SeqCst);
         self.watchdog_running.store(false, Ordering::SeqCst);
     }
 
     pub fn get_status_result(&self) -> VpnStatusResult {
         let status = self.get_status();
-        let server_id = self.server_id.lock().ok().and_then(|s| *s);
+        let server_id = self.get_server_id();
 
         let connected_at = if status == VpnStatusEnum::Connected {
             Some(self.get_connected_at())
@@ -147,12 +332,16 @@ impl VpnState {
         };
 
         let mode = self.get_current_mode();
+        let reason = self.last_transition().map(|t| t.reason);
+        let mtu = self.get_mtu().map(|m| m.tun_mtu);
 
         VpnStatusResult {
             status: status.as_str().to_string(),
             server_id,
             connected_at,
             mode,
+            reason,
+            mtu,
         }
     }
 
@@ -176,10 +365,139 @@ impl VpnState {
         }
     }
 
+    pub fn get_clash_api(&self) -> Option<ClashApi> {
+        self.clash_api.lock().ok().and_then(|api| api.clone())
+    }
+
+    pub fn set_clash_api(&self, api: Option<ClashApi>) {
+        if let Ok(mut current) = self.clash_api.lock() {
+            *current = api;
+        }
+    }
+
+    pub fn get_mtu(&self) -> Option<MtuMeasurement> {
+        self.mtu.lock().ok().and_then(|m| m.clone())
+    }
+
+    pub fn set_mtu(&self, measurement: Option<MtuMeasurement>) {
+        if let Ok(mut mtu) = self.mtu.lock() {
+            *mtu = measurement;
+        }
+    }
+
+    pub fn get_last_connect(&self) -> Option<(i32, ConnectConfig)> {
+        self.last_connect.lock().ok().and_then(|c| c.clone())
+    }
+
+    pub fn set_last_connect(&self, server_id: i32, config: ConnectConfig) {
+        if let Ok(mut last) = self.last_connect.lock() {
+            *last = Some((server_id, config));
+        }
+    }
+
+    pub fn get_session_nodes(&self) -> Vec<i32> {
+        self.session_nodes
+            .lock()
+            .map(|n| n.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_session_nodes(&self, nodes: Vec<i32>) {
+        if let Ok(mut current) = self.session_nodes.lock() {
+            *current = nodes;
+        }
+    }
+
+    pub fn get_server_id(&self) -> Option<i32> {
+        self.server_id.lock().ok().and_then(|s| *s)
+    }
+
+    pub fn get_kill_switch(&self) -> KillSwitchStatus {
+        self.kill_switch
+            .lock()
+            .map(|k| k.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_kill_switch(&self, status: KillSwitchStatus) {
+        if let Ok(mut current) = self.kill_switch.lock() {
+            *current = status;
+        }
+    }
+
+    pub fn get_connect_timing(&self) -> Option<ConnectTiming> {
+        self.connect_timing.lock().ok().and_then(|t| t.clone())
+    }
+
+    pub fn set_connect_timing(&self, timing: ConnectTiming) {
+        if let Ok(mut current) = self.connect_timing.lock() {
+            *current = Some(timing);
+        }
+    }
+
+    pub fn begin_active_session(&self, session: ActiveSession) {
+        if let Ok(mut active) = self.active_session.lock() {
+            *active = Some(session);
+        }
+    }
+
+    pub fn take_active_session(&self) -> Option<ActiveSession> {
+        self.active_session.lock().ok().and_then(|mut a| a.take())
+    }
+
+    /// 记录 Clash API 报告的累计流量与本轮速度
+    pub fn record_traffic(&self, traffic: &TrafficStats) {
+        if let Ok(mut active) = self.active_session.lock() {
+            if let Some(session) = active.as_mut() {
+                session.bytes_down = traffic.download_bytes.saturating_sub(session.base_down);
+                session.bytes_up = traffic.upload_bytes.saturating_sub(session.base_up);
+                session.peak_download_speed =
+                    session.peak_download_speed.max(traffic.download_speed);
+                session.peak_upload_speed = session.peak_upload_speed.max(traffic.upload_speed);
+            }
+        }
+    }
+
+    pub fn record_latency(&self, latency_ms: u32) {
+        if let Ok(mut active) = self.active_session.lock() {
+            if let Some(session) = active.as_mut() {
+                session.latency_sum_ms += latency_ms as u64;
+                session.latency_samples += 1;
+            }
+        }
+    }
+
+    pub fn get_network_baseline(&self) -> Option<NetworkSnapshot> {
+        self.network_baseline.lock().ok().and_then(|b| b.clone())
+    }
+
+    pub fn set_network_baseline(&self, snapshot: Option<NetworkSnapshot>) {
+        if let Ok(mut baseline) = self.network_baseline.lock() {
+            *baseline = snapshot;
+        }
+    }
+
+    pub fn get_cleanup_report(&self) -> Option<CleanupReport> {
+        self.cleanup_report.lock().ok().and_then(|r| r.clone())
+    }
+
+    pub fn set_cleanup_report(&self, report: CleanupReport) {
+        if let Ok(mut current) = self.cleanup_report.lock() {
+            *current = Some(report);
+        }
+    }
+
     pub fn take_child(&self) -> Option<CommandChild> {
         self.child.lock().ok().and_then(|mut c| c.take())
     }
 
+    pub fn child_pid(&self) -> Option<u32> {
+        self.child
+            .lock()
+            .ok()
+            .and_then(|c| c.as_ref().map(|c| c.pid()))
+    }
+
     pub fn set_child(&self, child: CommandChild) {
         if let Ok(mut c) = self.child.lock() {
             *c = Some(child);
@@ -190,6 +508,109 @@ impl VpnState {
 #[tauri::command]
 pub async fn check_vpn_status(
     state: tauri::State<'_, VpnState>,
-) -> Result<VpnStatusResult, String> {
+) -> std::result::Result<VpnStatusResult, String> {
     Ok(state.get_status_result())
 }
+
+/// 最近的状态迁移记录（含原因与时间戳）
+#[tauri::command]
+pub fn get_status_transitions(state: tauri::State<'_, VpnState>) -> Vec<StatusTransition> {
+    state.get_transitions()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VpnStatusEnum::{self, *};
+
+    const ALL: [VpnStatusEnum; 6] = [
+        Disconnected,
+        Connecting,
+        Connected,
+        Reconnecting,
+        Disconnecting,
+        Failed,
+    ];
+
+    #[test]
+    fn transition_table() {
+        let allowed = [
+            (Disconnected, Connecting),
+            (Connecting, Connected),
+            (Connecting, Disconnecting),
+            (Connecting, Failed),
+            (Connected, Reconnecting),
+            (Connected, Disconnecting),
+            (Connected, Failed),
+            (Reconnecting, Connected),
+            (Reconnecting, Disconnecting),
+            (Reconnecting, Failed),
+            (Disconnecting, Disconnected),
+            (Disconnecting, Failed),
+            (Failed, Connecting),
+            (Failed, Reconnecting),
+            (Failed, Disconnected),
+        ];
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn in_progress_states_can_fail() {
+        for from in [Connecting, Connected, Reconnecting, Disconnecting] {
+            assert!(from.can_transition_to(Failed), "{:?}", from);
+        }
+        assert!(!Disconnected.can_transition_to(Failed));
+    }
+
+    #[test]
+    fn no_self_transitions() {
+        for state in ALL {
+            assert!(!state.can_transition_to(state), "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn rejected_attempt_keeps_running_token() {
+        let state = super::VpnState::new();
+        let (record, first) = state.begin_connect_attempt(Connecting, "ui").unwrap();
+        assert_eq!((record.from, record.to), (Disconnected, Connecting));
+        // 并发的第二个请求被拒绝，不得取消第一个
+        assert!(state
+            .begin_connect_attempt(Connecting, "autoconnect")
+            .is_err());
+        assert!(!first.is_cancelled());
+        assert_eq!(state.get_status(), Connecting);
+
+        state.cancel_connect_attempt();
+        assert!(first.is_cancelled());
+    }
+
+    #[test]
+    fn new_attempt_cancels_previous_token() {
+        let state = super::VpnState::new();
+        let (_, first) = state.begin_connect_attempt(Connecting, "ui").unwrap();
+        state.transition(Connected, "up").unwrap();
+        let (_, second) = state.begin_connect_attempt(Reconnecting, "lost").unwrap();
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+    }
+
+    #[test]
+    fn status_strings_match_serde() {
+        for state in ALL {
+            assert_eq!(
+                serde_json::to_value(state).unwrap(),
+                serde_json::json!(state.as_str())
+            );
+        }
+    }
+}
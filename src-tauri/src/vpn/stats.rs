@@ -1,6 +1,6 @@
 use tauri::{AppHandle, Manager};
 use tracing::debug;
-use crate::constants::{SINGBOX_API_PORT_TUN, SINGBOX_API_PORT_SOCKS};
+use crate::vpn::clash_api::ClashApi;
 use crate::vpn::state::VpnState;
 
 /// 测试延迟
@@ -14,15 +14,11 @@ pub async fn ping_server(app: AppHandle, host: String, port: u16) -> Result<i32,
     
     // 如果已连接，尝试通过 API 获取延迟
     if state.is_connected() {
-        // 根据当前模式选择对应的 API 端口
-        let api_port = if state.get_current_mode() == "tun" {
-            SINGBOX_API_PORT_TUN
-        } else {
-            SINGBOX_API_PORT_SOCKS
-        };
-
-        if let Some(latency) = get_latency_via_api(api_port).await {
-            return Ok(latency);
+        // 使用本次会话生成的 API 端口与 secret
+        if let Some(api) = state.get_clash_api() {
+            if let Some(latency) = get_latency_via_api(&api).await {
+                return Ok(latency);
+            }
         }
     }
 
@@ -44,10 +40,9 @@ pub async fn ping_server(app: AppHandle, host: String, port: u16) -> Result<i32,
 }
 
 /// 通过 sing-box API 获取延迟
-async fn get_latency_via_api(port: u16) -> Option<i32> {
-    let url = format!(
-        "http://127.0.0.1:{}/proxies/proxy/delay?timeout=5000&url=http://www.gstatic.com/generate_204",
-        port
+async fn get_latency_via_api(api: &ClashApi) -> Option<i32> {
+    let url = api.url(
+        "/proxies/proxy/delay?timeout=5000&url=http://www.gstatic.com/generate_204",
     );
 
     let client = reqwest::Client::builder()
@@ -55,7 +50,12 @@ async fn get_latency_via_api(port: u16) -> Option<i32> {
         .build()
         .ok()?;
 
-    let response = client.get(&url).send().await.ok()?;
+    let response = client
+        .get(&url)
+        .bearer_auth(&api.secret)
+        .send()
+        .await
+        .ok()?;
     
     if !response.status().is_success() {
         return None;
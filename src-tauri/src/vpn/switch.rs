@@ -0,0 +1,98 @@
+//! 节点热切换模块
+//! 通过 Clash API 切换 "proxy" selector 的当前节点，TUN 网卡与路由保持不变，无需重新提权
+
+use std::time::Duration;
+
+use serde_json::json;
+use tauri::{AppHandle, Emitter};
+use tracing::info;
+
+use crate::error::{CommandResult, ErrorPayload, Result, VpnError};
+
+use super::clash_api::ClashApi;
+use super::history;
+use super::monitor::{emit_log, emit_status_change};
+use super::session;
+use super::singbox::node_tag;
+use super::state::{VpnState, VpnStatusEnum};
+
+/// 将 selector 切换到指定出站
+async fn select_outbound(api: &ClashApi, tag: &str) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()?;
+
+    let response = client
+        .put(api.url("/proxies/proxy"))
+        .bearer_auth(&api.secret)
+        .json(&json!({ "name": tag }))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(VpnError::Network(format!(
+            "Clash API rejected selector switch: {}",
+            response.status()
+        )))
+    }
+}
+
+/// 切换到当前会话内的另一个节点（连接时以 `alternates` 传入的节点）
+#[tauri::command]
+pub async fn switch_server(
+    app_handle: AppHandle,
+    state: tauri::State<'_, VpnState>,
+    server_id: i32,
+) -> CommandResult<String> {
+    if state.get_status() != VpnStatusEnum::Connected {
+        return Err(VpnError::InvalidTransition("switch requires connected".to_string()).into());
+    }
+
+    let previous = state.get_server_id();
+    if previous == Some(server_id) {
+        return Ok("Already using server".into());
+    }
+
+    // 节点不在本次会话的 selector 中时只能重新连接
+    let index = state
+        .get_session_nodes()
+        .iter()
+        .position(|id| *id == server_id)
+        .ok_or_else(|| {
+            VpnError::InvalidServer(format!("Server {} is not part of this session", server_id))
+        })?;
+    let api = state
+        .get_clash_api()
+        .ok_or_else(|| VpnError::Connection("Clash API unavailable".to_string()))?;
+
+    select_outbound(&api, &node_tag(index))
+        .await
+        .map_err(ErrorPayload::from)?;
+
+    info!(from = ?previous, to = server_id, "Switched server");
+    state.set_server_id(Some(server_id));
+    // 流量与时长按节点分别计入历史
+    history::restart(&app_handle, &state, "server switched");
+
+    // 自动重连以切换后的节点为准
+    if let Some((current_id, config)) = state.get_last_connect() {
+        if let Some(promoted) = config.promote(current_id, server_id) {
+            state.set_last_connect(server_id, promoted);
+        }
+    }
+    session::save(&state);
+
+    let _ = app_handle.emit(
+        "vpn-server-switched",
+        json!({ "from": previous, "to": server_id }),
+    );
+    emit_status_change(&app_handle, &state);
+    emit_log(
+        &app_handle,
+        "info",
+        &format!("Switched to server {}", server_id),
+    );
+    Ok("Switched".into())
+}
@@ -0,0 +1,156 @@
+//! 断开清理模块
+//! 按顺序关闭系统代理、结束 sing-box (SIGTERM → 限时等待 → SIGKILL)、移除残留 TUN 网卡，
+//! 再对照连接前的网络快照确认进程、TUN 网卡、默认路由、DNS 与系统代理已复原，生成清理报告
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+use crate::constants::teardown;
+
+use super::platform::{self, NetworkSnapshot};
+
+/// 清理后仍残留的资源
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupIssue {
+    /// process / tun_interface / routes / dns / system_proxy
+    pub resource: &'static str,
+    pub detail: String,
+}
+
+/// 清理报告（同时作为 `vpn-cleanup-warning` 事件负载）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub mode: String,
+    /// 进程未在宽限期内退出，动用了强制结束
+    pub forced_kill: bool,
+    /// 与连接前快照比较过路由/DNS/代理（接管的会话没有快照）
+    pub baseline_checked: bool,
+    pub issues: Vec<CleanupIssue>,
+    pub duration_ms: u64,
+}
+
+impl CleanupReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// 结束 sing-box 并校验系统网络状态（阻塞，需在阻塞线程池或普通线程中调用）
+pub fn run(mode: &str, baseline: Option<&NetworkSnapshot>) -> CleanupReport {
+    let start = Instant::now();
+    let mut report = CleanupReport {
+        mode: mode.to_string(),
+        ..Default::default()
+    };
+
+    platform::set_system_socks_proxy(false);
+
+    // 登记的进程 (含 SOCKS 模式的 sidecar) 先收到 SIGTERM，sing-box 借此撤销自动路由
+    let leftover =
+        match platform::owned::terminate_all(Duration::from_millis(teardown::STOP_GRACE_MS)) {
+            Ok((forced, leftover)) => {
+                report.forced_kill = forced;
+                Some(leftover)
+            }
+            Err(e) => {
+                // 信号未送达 (如取消了提权)：不再重复请求授权，如实报告
+                report.issues.push(CleanupIssue {
+                    resource: "process",
+                    detail: format!("failed to stop sing-box: {}", e),
+                });
+                None
+            }
+        };
+    if leftover.is_some() {
+        if mode == "tun" {
+            if let Err(e) = platform::stop_singbox_tun_as_root() {
+                warn!("TUN stop error: {}", e);
+            }
+        }
+        platform::force_cleanup();
+    }
+    let leftover =
+        leftover.unwrap_or_else(|| platform::owned::alive().iter().map(|p| p.pid).collect());
+
+    for pid in leftover {
+        report.issues.push(CleanupIssue {
+            resource: "process",
+            detail: format!("sing-box (pid {}) still running", pid),
+        });
+    }
+    if platform::tun_interface_present() {
+        report.issues.push(CleanupIssue {
+            resource: "tun_interface",
+            detail: "TUN interface still present".to_string(),
+        });
+    }
+    if let Some(baseline) = baseline {
+        report.baseline_checked = true;
+        compare(baseline, &platform::network_snapshot(), &mut report.issues);
+    }
+
+    report.duration_ms = start.elapsed().as_millis() as u64;
+    if report.is_clean() {
+        info!(
+            forced = report.forced_kill,
+            duration_ms = report.duration_ms,
+            "Cleanup verified"
+        );
+    } else {
+        warn!(issues = ?report.issues, "Cleanup left resources behind");
+    }
+    report
+}
+
+/// 与连接前快照比较；只报告新增的默认路由，网络切换导致的路由消失不算残留
+fn compare(before: &NetworkSnapshot, after: &NetworkSnapshot, issues: &mut Vec<CleanupIssue>) {
+    for route in after
+        .default_routes
+        .iter()
+        .filter(|r| !before.default_routes.contains(r))
+    {
+        issues.push(CleanupIssue {
+            resource: "routes",
+            detail: format!("default route added during session: {}", route),
+        });
+    }
+    if after.dns_servers != before.dns_servers {
+        issues.push(CleanupIssue {
+            resource: "dns",
+            detail: format!(
+                "DNS servers {:?} differ from pre-connect {:?}",
+                after.dns_servers, before.dns_servers
+            ),
+        });
+    }
+    if after.system_proxy != before.system_proxy {
+        issues.push(CleanupIssue {
+            resource: "system_proxy",
+            detail: format!(
+                "system proxy {:?} differs from pre-connect {:?}",
+                after.system_proxy, before.system_proxy
+            ),
+        });
+    }
+}
+
+/// 有残留时发送 `vpn-cleanup-warning`
+#[cfg(feature = "gui")]
+pub fn emit_warning(app: &AppHandle, report: &CleanupReport) {
+    if !report.is_clean() {
+        let _ = app.emit("vpn-cleanup-warning", report);
+    }
+}
+
+/// 最近一次断开清理的报告
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn get_last_cleanup_report(
+    state: tauri::State<'_, super::state::VpnState>,
+) -> Option<CleanupReport> {
+    state.get_cleanup_report()
+}
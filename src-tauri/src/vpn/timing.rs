@@ -0,0 +1,159 @@
+//! 连接阶段计时模块
+//! 连接过程中逐阶段发出 `vpn-connect-phase` 事件（含耗时），并保留最近一次连接的耗时明细，
+//! 用于定位慢连接卡在哪一步
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::info;
+
+use super::state::VpnState;
+
+/// 连接阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectPhase {
+    Cleanup,
+    KillSwitch,
+    ProbingMtu,
+    ResolvingServer,
+    GeneratingConfig,
+    VerifyingSignature,
+    RequestingPrivileges,
+    Spawning,
+    WaitingForInbound,
+    VerifyingTunnel,
+}
+
+/// 单个阶段的耗时
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub phase: ConnectPhase,
+    pub duration_ms: u64,
+    /// completed / failed
+    pub status: &'static str,
+}
+
+/// 一次连接尝试的耗时明细
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectTiming {
+    /// 开始时间 (秒)
+    pub started_at: u64,
+    pub mode: String,
+    pub phases: Vec<PhaseTiming>,
+    pub total_ms: u64,
+    /// connected / failed / cancelled
+    pub outcome: &'static str,
+    pub error: Option<String>,
+}
+
+/// `vpn-connect-phase` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectPhaseEvent {
+    pub phase: ConnectPhase,
+    /// started / completed / failed
+    pub status: &'static str,
+    /// 本阶段耗时，started 时为空
+    pub duration_ms: Option<u64>,
+    /// 距连接开始的时长
+    pub elapsed_ms: u64,
+}
+
+/// 连接计时器；进入下一阶段时自动结束上一阶段
+pub struct ConnectTimer {
+    app: AppHandle,
+    start: Instant,
+    started_at: u64,
+    mode: String,
+    current: Option<(ConnectPhase, Instant)>,
+    phases: Vec<PhaseTiming>,
+}
+
+impl ConnectTimer {
+    pub fn new(app: &AppHandle, mode: &str) -> Self {
+        Self {
+            app: app.clone(),
+            start: Instant::now(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            mode: mode.to_string(),
+            current: None,
+            phases: Vec::new(),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    fn emit(&self, phase: ConnectPhase, status: &'static str, duration_ms: Option<u64>) {
+        let _ = self.app.emit(
+            "vpn-connect-phase",
+            ConnectPhaseEvent {
+                phase,
+                status,
+                duration_ms,
+                elapsed_ms: self.elapsed_ms(),
+            },
+        );
+    }
+
+    fn end_current(&mut self, status: &'static str) {
+        if let Some((phase, since)) = self.current.take() {
+            let duration_ms = since.elapsed().as_millis() as u64;
+            self.phases.push(PhaseTiming {
+                phase,
+                duration_ms,
+                status,
+            });
+            self.emit(phase, status, Some(duration_ms));
+        }
+    }
+
+    /// 进入新阶段
+    pub fn phase(&mut self, phase: ConnectPhase) {
+        self.end_current("completed");
+        self.current = Some((phase, Instant::now()));
+        self.emit(phase, "started", None);
+    }
+
+    /// 结束计时并记录到状态；失败或取消时当前阶段记为 failed
+    pub fn finish(mut self, state: &VpnState, outcome: &'static str, error: Option<String>) {
+        self.end_current(if outcome == "connected" {
+            "completed"
+        } else {
+            "failed"
+        });
+
+        let timing = ConnectTiming {
+            started_at: self.started_at,
+            mode: self.mode,
+            total_ms: self.start.elapsed().as_millis() as u64,
+            phases: self.phases,
+            outcome,
+            error,
+        };
+        let breakdown = timing
+            .phases
+            .iter()
+            .map(|p| format!("{:?}={}ms", p.phase, p.duration_ms))
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!(
+            outcome,
+            total_ms = timing.total_ms,
+            "Connect timing: {}",
+            breakdown
+        );
+        state.set_connect_timing(timing);
+    }
+}
+
+/// 最近一次连接（含重连尝试）的耗时明细
+#[tauri::command]
+pub fn get_last_connect_timing(state: tauri::State<'_, VpnState>) -> Option<ConnectTiming> {
+    state.get_connect_timing()
+}
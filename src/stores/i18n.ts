@@ -35,6 +35,7 @@ const translations = {
       download: "Download",
       upload: "Upload",
       latency: "Latency",
+      mtu: "MTU",
       ip: "IP Address",
       loading: "Loading...",
       hidden: "Hidden",
@@ -242,6 +243,7 @@ const translations = {
       download: "下载",
       upload: "上传",
       latency: "延迟",
+      mtu: "MTU",
       ip: "IP 地址",
       loading: "加载中...",
       hidden: "已隐藏",
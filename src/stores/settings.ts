@@ -1,5 +1,6 @@
 import { defineStore } from "pinia";
 import { ref, watch } from "vue";
+import { invoke } from "@tauri-apps/api/core";
 import type { VpnSettings, ConnectionMode, DnsMode } from "@/types";
 import { getItem, setItem } from "@/utils/storage";
 
@@ -25,6 +26,17 @@ export const useSettingsStore = defineStore("settings", () => {
     { deep: true }
   );
 
+  // 自动重连由后端执行，只同步开关，重试次数与退避参数保留后端已保存的值
+  watch(
+    () => settings.value.autoReconnect,
+    (enabled) => {
+      invoke("set_reconnect_enabled", { enabled }).catch((e) =>
+        console.error("Failed to sync reconnect policy:", e)
+      );
+    },
+    { immediate: true }
+  );
+
   // 监听主题变化
   watch(
     theme,
@@ -18,20 +18,28 @@ import type {
   VpnStatusEvent,
   VpnConnectionErrorEvent,
   VpnProcessTerminatedEvent,
+  VpnConnectPhaseEvent,
+  ConnectPhase,
+  CleanupReport,
+  FailingNode,
+  MtuMeasurement,
   HelperResult,
   HelperStatusResult,
   TunPrecheckResult,
   VpnStatusResult,
   ConnectivityResult,
+  ConnectOptions,
+  ErrorPayload,
 } from "./vpn/types";
 import {
   isValidVpnStatus,
   isValidHelperStatus,
+  isErrorPayload,
+  formatVpnError,
   DEFAULT_CONNECTION_STATS,
 } from "./vpn/constants";
 import { useDailyLimits } from "./vpn/useDailyLimits";
 import { useVpnMonitor } from "./vpn/useVpnMonitor";
-import { useVpnReconnect } from "./vpn/useVpnReconnect";
 
 // 其他 Store 导入
 import { useLogsStore } from "./logs";
@@ -54,7 +62,11 @@ export const useVpnStore = defineStore("vpn", () => {
   const isVpnBusy = ref(false);
   const isHelperBusy = ref(false);
   const error = ref<string | null>(null);
+  // 最近一次结构化错误，供界面按错误码给出处理入口
+  const errorPayload = ref<ErrorPayload | null>(null);
   const isUserDisconnecting = ref(false);
+  // 连接进行中的当前阶段，连接结束后清空
+  const connectPhase = ref<ConnectPhase | null>(null);
 
   const stats = ref<ConnectionStats>({ ...DEFAULT_CONNECTION_STATS });
 
@@ -66,6 +78,10 @@ export const useVpnStore = defineStore("vpn", () => {
     status: null,
     error: null,
     terminated: null,
+    phase: null,
+    cleanup: null,
+    crashLoop: null,
+    mtu: null,
   });
 
   let connectedTimeTimer: number | null = null;
@@ -80,7 +96,6 @@ export const useVpnStore = defineStore("vpn", () => {
   // TS修复: 显式声明可能为 undefined
   let _dailyLimits: ReturnType<typeof useDailyLimits> | undefined;
   let _monitor: ReturnType<typeof useVpnMonitor> | undefined;
-  let _reconnect: ReturnType<typeof useVpnReconnect> | undefined;
 
   function initSubModules() {
     if (!_dailyLimits) {
@@ -89,9 +104,6 @@ export const useVpnStore = defineStore("vpn", () => {
     if (!_monitor) {
       _monitor = useVpnMonitor(stats, status);
     }
-    if (!_reconnect) {
-      _reconnect = useVpnReconnect(connect, status);
-    }
   }
 
   // ============ Getters ============
@@ -168,11 +180,11 @@ export const useVpnStore = defineStore("vpn", () => {
           ? result.connected_at * 1000
           : Date.now();
         startConnectedTimeCounter();
+        stats.value.mtu = result.mtu ?? null;
 
         // 重新初始化所有子模块并重启监控
         initSubModules();
         // TS修复: 确认 _monitor 已初始化
-        _monitor!.startTrafficMonitor();
         _monitor!.startPingLoop();
         startRealtimeUsageCheck();
 
@@ -266,24 +278,31 @@ export const useVpnStore = defineStore("vpn", () => {
       listeners.value.status = await listen<VpnStatusEvent>(
         "vpn-status-change",
         (event) => {
-          if (!isValidVpnStatus(event.payload.status)) return;
-          const newStatus = event.payload.status;
+          // 后端重连中/失败映射到现有 UI 状态
+          const raw = event.payload.status;
+          const mapped =
+            raw === "reconnecting"
+              ? "connecting"
+              : raw === "failed"
+                ? "disconnected"
+                : raw;
+          if (!isValidVpnStatus(mapped)) return;
+          const newStatus = mapped;
           console.log(`VPN status event: ${newStatus}`);
 
           status.value = newStatus;
           isVpnBusy.value = false; // 收到明确状态变更，解除繁忙
+          if (newStatus !== "connecting") connectPhase.value = null;
 
           if (newStatus === "connected") {
             connectedAt = Date.now();
             startConnectedTimeCounter();
-            _monitor!.startTrafficMonitor();
             _monitor!.startPingLoop();
             startRealtimeUsageCheck();
             isUserDisconnecting.value = false;
           } else if (newStatus === "disconnected") {
             stopConnectedTimeCounter();
             stopRealtimeUsageCheck();
-            _monitor!.stopTrafficMonitor();
             _monitor!.stopPingLoop();
             if (!isUserDisconnecting.value) _dailyLimits!.accumulateUsage();
             resetStats();
@@ -296,9 +315,18 @@ export const useVpnStore = defineStore("vpn", () => {
       listeners.value.error = await listen<VpnConnectionErrorEvent>(
         "vpn-connection-error",
         (event) => {
-          const { error: errorMsg, fatal } = event.payload;
-          console.error(`VPN connection error: ${errorMsg}`);
-          error.value = `Connection failed: ${errorMsg}`;
+          const { error: errorMsg, fatal, payload, diagnosis } = event.payload;
+          console.error(`VPN connection error: ${errorMsg}`, diagnosis);
+          errorPayload.value = payload ?? null;
+          if (diagnosis) {
+            error.value = payload
+              ? `${payload.message}: ${diagnosis.hint}`
+              : diagnosis.hint;
+          } else {
+            error.value = payload
+              ? formatVpnError(payload)
+              : `Connection failed: ${errorMsg}`;
+          }
           isVpnBusy.value = false;
           if (fatal) {
             status.value = "disconnected";
@@ -308,6 +336,50 @@ export const useVpnStore = defineStore("vpn", () => {
         }
       );
 
+      // 连接阶段事件
+      listeners.value.phase = await listen<VpnConnectPhaseEvent>(
+        "vpn-connect-phase",
+        (event) => {
+          const { phase, status: phaseStatus, duration_ms } = event.payload;
+          if (phaseStatus === "started") {
+            connectPhase.value = phase;
+          } else if (duration_ms !== null) {
+            console.log(`Connect phase ${phase} ${phaseStatus} in ${duration_ms}ms`);
+          }
+        }
+      );
+
+      // 断开后仍有残留（进程、TUN 网卡、路由、DNS、系统代理）
+      listeners.value.cleanup = await listen<CleanupReport>(
+        "vpn-cleanup-warning",
+        (event) => {
+          for (const issue of event.payload.issues) {
+            logs.addLog("warn", `Cleanup incomplete (${issue.resource}): ${issue.detail}`);
+          }
+        }
+      );
+
+      // 节点反复异常退出，后端已停止自动重连；提示文案经 vpn-connection-error 下发
+      listeners.value.crashLoop = await listen<FailingNode>(
+        "vpn-crash-loop",
+        (event) => {
+          const node = event.payload;
+          logs.addLog(
+            "error",
+            `Server ${node.server_id} exited ${node.exits} times (${node.cause}), marked as failing`
+          );
+        }
+      );
+
+      // MTU 探测结果，连接过程中下发
+      listeners.value.mtu = await listen<MtuMeasurement>("vpn-mtu", (event) => {
+        stats.value.mtu = event.payload.tun_mtu;
+        logs.addLog(
+          "info",
+          `TUN MTU ${event.payload.tun_mtu} (path ${event.payload.path_mtu}${event.payload.cached ? ", cached" : ""})`
+        );
+      });
+
       // 进程终止事件
       listeners.value.terminated = await listen<VpnProcessTerminatedEvent>(
         "vpn-process-terminated",
@@ -320,11 +392,9 @@ export const useVpnStore = defineStore("vpn", () => {
             resetStats();
             isVpnBusy.value = false;
 
+            // 自动重连由后端负责，收到该事件说明后端不再重连
             if (event.payload.reason === "fatal_error") {
               error.value = "Connection terminated unexpectedly.";
-            } else {
-              if (_reconnect!.shouldAutoReconnect())
-                _reconnect!.scheduleReconnect();
             }
           }
         }
@@ -484,6 +554,7 @@ export const useVpnStore = defineStore("vpn", () => {
       isVpnBusy.value = true;
       isUserDisconnecting.value = false;
       error.value = null;
+      errorPayload.value = null;
       resetStats();
 
       try {
@@ -495,15 +566,26 @@ export const useVpnStore = defineStore("vpn", () => {
           dnsConfig = `custom:${settingsStore.settings.customDns}`;
         }
 
-        await invoke("connect_hysteria", {
-          serverId: server.id,
+        const options: ConnectOptions = {
+          server_id: server.id,
           domain: server.domain,
           port: server.port,
           password: server.password || "",
           mode: settingsStore.settings.connectionMode,
-          serverMtu: settingsStore.settings.mtu,
-          serverDns: dnsConfig,
-        });
+          server_mtu: settingsStore.settings.mtu,
+          server_dns: dnsConfig,
+          kill_switch: settingsStore.settings.killSwitch,
+          // 其余节点一并下发，连接后可热切换
+          alternates: serversStore.servers
+            .filter((s) => s.id !== server.id && s.domain && s.password)
+            .map((s) => ({
+              server_id: s.id,
+              domain: s.domain,
+              port: s.port,
+              password: s.password,
+            })),
+        };
+        await invoke("connect_hysteria", { options });
 
         // 短暂等待后同步一次状态
         await new Promise((resolve) => setTimeout(resolve, 500));
@@ -515,7 +597,8 @@ export const useVpnStore = defineStore("vpn", () => {
         }
       } catch (e) {
         console.error("[VPN] Connect error:", e);
-        error.value = String(e);
+        errorPayload.value = isErrorPayload(e) ? e : null;
+        error.value = formatVpnError(e);
         status.value = "disconnected";
         isVpnBusy.value = false;
       }
@@ -533,7 +616,6 @@ export const useVpnStore = defineStore("vpn", () => {
       status.value = "disconnecting"; // 立即设置 UI 状态
       isVpnBusy.value = true;
       isUserDisconnecting.value = true;
-      _reconnect!.cancelReconnect();
 
       try {
         _dailyLimits!.accumulateUsage();
@@ -550,10 +632,30 @@ export const useVpnStore = defineStore("vpn", () => {
     });
   }
 
+  /**
+   * 已连接时热切换节点（不重建 TUN），失败返回 false 由调用方回退到重连
+   */
+  async function switchServer(serverId: number): Promise<boolean> {
+    if (status.value !== "connected") return false;
+    try {
+      await invoke("switch_server", { serverId });
+      return true;
+    } catch (e) {
+      console.warn("[VPN] Hot switch failed, falling back to reconnect:", e);
+      return false;
+    }
+  }
+
   async function cancelConnect() {
     if (!canCancel.value) return;
     isUserDisconnecting.value = true;
-    await disconnect();
+    try {
+      // 后端中止进行中的解析/授权/进程启动并完成清理
+      await invoke("cancel_connect");
+    } catch (e) {
+      console.error("[VPN] Cancel connect error:", e);
+    }
+    await syncVpnStatus();
   }
 
   // ============ 辅助功能 ============
@@ -665,11 +767,14 @@ export const useVpnStore = defineStore("vpn", () => {
       status: null,
       error: null,
       terminated: null,
+      phase: null,
+      cleanup: null,
+      crashLoop: null,
+      mtu: null,
     };
     stopConnectedTimeCounter();
     stopRealtimeUsageCheck();
     _monitor?.cleanup();
-    _reconnect?.cancelReconnect();
     listenersInitialized = false;
   }
 
@@ -692,6 +797,7 @@ export const useVpnStore = defineStore("vpn", () => {
   }
   function clearError() {
     error.value = null;
+    errorPayload.value = null;
   }
 
   return {
@@ -701,6 +807,8 @@ export const useVpnStore = defineStore("vpn", () => {
     isVpnBusy,
     isHelperBusy,
     error,
+    errorPayload,
+    connectPhase,
     stats,
     // TS修复: Getter 返回值需使用 ! 断言
     get dailyUsage() {
@@ -735,6 +843,7 @@ export const useVpnStore = defineStore("vpn", () => {
     precheckTunPermission,
     connect,
     disconnect,
+    switchServer,
     cancelConnect,
     initEventListeners,
     cleanup,
@@ -1,7 +1,7 @@
 // src/stores/vpn/constants.ts
 // VPN Store 常量定义
 
-import type { VpnStatus, HelperStatus } from "./types";
+import type { VpnStatus, HelperStatus, ErrorPayload } from "./types";
 
 // ============ 存储键 ============
 
@@ -15,17 +15,6 @@ export const USER_DAILY_TRAFFIC_LIMIT = 1 * 1024 * 1024 * 1024;
 /** 普通用户每日时间限制: 2小时 */
 export const USER_DAILY_TIME_LIMIT = 2 * 60 * 60;
 
-// ============ 自动重连配置 ============
-
-/** 最大重连尝试次数 */
-export const RECONNECT_MAX_ATTEMPTS = 5;
-
-/** 重连基础延迟 (1秒) */
-export const RECONNECT_BASE_DELAY = 1000;
-
-/** 重连最大延迟 (30秒) */
-export const RECONNECT_MAX_DELAY = 30000;
-
 // ============ 类型守卫 ============
 
 export const VPN_STATUSES = [
@@ -57,6 +46,28 @@ export function isValidHelperStatus(status: string): status is HelperStatus {
     return HELPER_STATUSES.includes(status as HelperStatus);
 }
 
+/**
+ * 检查是否为后端返回的结构化错误
+ */
+export function isErrorPayload(e: unknown): e is ErrorPayload {
+    return (
+        typeof e === "object" &&
+        e !== null &&
+        typeof (e as ErrorPayload).code === "string" &&
+        typeof (e as ErrorPayload).message === "string"
+    );
+}
+
+/**
+ * 将命令错误转换为提示文本（兼容仍返回字符串的命令）
+ */
+export function formatVpnError(e: unknown): string {
+    if (isErrorPayload(e)) {
+        return e.action ? `${e.message}: ${e.action}` : e.message;
+    }
+    return String(e);
+}
+
 // ============ 默认值 ============
 
 export const DEFAULT_CONNECTION_STATS = {
@@ -67,4 +78,5 @@ export const DEFAULT_CONNECTION_STATS = {
     connectedTime: 0,
     totalDownload: 0,
     totalUpload: 0,
+    mtu: null,
 };
@@ -10,12 +10,21 @@ export type {
     LatencyEvent,
     VpnStatusEvent,
     VpnConnectionErrorEvent,
+    VpnConnectPhaseEvent,
+    ConnectPhase,
+    CleanupReport,
+    FailingNode,
+    ConnectNode,
+    ConnectOptions,
+    MtuMeasurement,
     VpnProcessTerminatedEvent,
     HelperResult,
     HelperStatusResult,
     TunPrecheckResult,
     VpnStatusResult,
     ConnectivityResult,
+    ErrorPayload,
+    SingboxDiagnosis,
     DailyUsage,
     UsageLimitCheckResult,
     VpnEventListeners,
@@ -27,17 +36,15 @@ export {
     DAILY_USAGE_KEY,
     USER_DAILY_TRAFFIC_LIMIT,
     USER_DAILY_TIME_LIMIT,
-    RECONNECT_MAX_ATTEMPTS,
-    RECONNECT_BASE_DELAY,
-    RECONNECT_MAX_DELAY,
     VPN_STATUSES,
     HELPER_STATUSES,
     isValidVpnStatus,
     isValidHelperStatus,
+    isErrorPayload,
+    formatVpnError,
     DEFAULT_CONNECTION_STATS,
 } from "./constants";
 
 // Composables 导出
 export { useDailyLimits } from "./useDailyLimits";
 export { useVpnMonitor } from "./useVpnMonitor";
-export { useVpnReconnect } from "./useVpnReconnect";
@@ -43,9 +43,83 @@ export interface VpnStatusEvent {
     connected_at: number | null;
 }
 
+/** sing-box 输出的失败诊断 */
+export interface SingboxDiagnosis {
+    kind:
+        | "clock_skew"
+        | "tls"
+        | "auth"
+        | "udp_blocked"
+        | "dns"
+        | "port_in_use"
+        | "permission"
+        | "binary_missing"
+        | "config";
+    hint: string;
+    evidence: string;
+}
+
 export interface VpnConnectionErrorEvent {
     error: string;
     fatal: boolean;
+    payload?: ErrorPayload;
+    diagnosis?: SingboxDiagnosis | null;
+}
+
+/** 连接阶段 */
+export type ConnectPhase =
+    | "cleanup"
+    | "kill_switch"
+    | "probing_mtu"
+    | "resolving_server"
+    | "generating_config"
+    | "verifying_signature"
+    | "requesting_privileges"
+    | "spawning"
+    | "waiting_for_inbound"
+    | "verifying_tunnel";
+
+export interface VpnConnectPhaseEvent {
+    phase: ConnectPhase;
+    status: "started" | "completed" | "failed";
+    /** 本阶段耗时，started 时为 null */
+    duration_ms: number | null;
+    /** 距连接开始的时长 */
+    elapsed_ms: number;
+}
+
+/** 断开清理报告，`vpn-cleanup-warning` 事件负载 */
+export interface CleanupReport {
+    mode: string;
+    forced_kill: boolean;
+    baseline_checked: boolean;
+    issues: {
+        resource: "process" | "tun_interface" | "routes" | "dns" | "system_proxy";
+        detail: string;
+    }[];
+    duration_ms: number;
+}
+
+/** 反复异常退出而被标记为故障的节点，`vpn-crash-loop` 事件负载 */
+export interface FailingNode {
+    server_id: number;
+    /** 标记起止时间 (秒) */
+    since: number;
+    until: number;
+    exits: number;
+    cause: string;
+    detail: string;
+}
+
+/** MTU 为 auto 时的探测结果，`vpn-mtu` 事件负载 */
+export interface MtuMeasurement {
+    /** 到节点的路径 MTU (IP 层) */
+    path_mtu: number;
+    /** 扣除隧道开销后用于 TUN 的 MTU */
+    tun_mtu: number;
+    network: string;
+    cached: boolean;
+    measured_at: number;
 }
 
 export interface VpnProcessTerminatedEvent {
@@ -55,6 +129,16 @@ export interface VpnProcessTerminatedEvent {
 
 // ============ API 响应类型 ============
 
+/** 后端命令返回的结构化错误 */
+export interface ErrorPayload {
+    /** 稳定错误码，如 PORT_IN_USE / AUTH_REJECTED */
+    code: string;
+    category: "config" | "connection" | "network" | "permission" | "system" | "state";
+    message: string;
+    detail: string | null;
+    action: string | null;
+}
+
 export interface HelperResult {
     success: boolean;
     message: string;
@@ -71,10 +155,38 @@ export interface TunPrecheckResult {
     platform: string;
 }
 
+/** 节点连接参数，对应后端 ServerNode */
+export interface ConnectNode {
+    server_id: number;
+    domain: string;
+    port: number;
+    password: string;
+}
+
+/** connect_hysteria 参数，字段名与后端 ConnectOptions 一致 */
+export interface ConnectOptions extends ConnectNode {
+    mode: string;
+    /** 0 = auto */
+    server_mtu: number;
+    server_dns: string;
+    tun_options?: {
+        stack?: string;
+        strict_route?: boolean;
+        route_address?: string[];
+        route_exclude_address?: string[];
+    };
+    /** 空 = 不使用，"auto" = 环境变量探测，其余为 socks5:// URL */
+    upstream_proxy?: string;
+    kill_switch?: boolean;
+    alternates?: ConnectNode[];
+}
+
 export interface VpnStatusResult {
     status: string;
     server_id: number | null;
     connected_at: number | null;
+    /** MTU 为 auto 时探测得到的 TUN MTU */
+    mtu: number | null;
 }
 
 export interface ConnectivityResult {
@@ -110,6 +222,10 @@ export interface VpnEventListeners {
     status: UnlistenFn | null;
     error: UnlistenFn | null;
     terminated: UnlistenFn | null;
+    phase: UnlistenFn | null;
+    cleanup: UnlistenFn | null;
+    crashLoop: UnlistenFn | null;
+    mtu: UnlistenFn | null;
 }
 
 // ============ 连接统计类型 ============
@@ -122,4 +238,6 @@ export interface ConnectionStats {
     connectedTime: number;
     totalDownload: number;
     totalUpload: number;
+    /** 探测得到的 TUN MTU，未探测时为 null */
+    mtu: number | null;
 }
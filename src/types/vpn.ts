@@ -27,6 +27,7 @@ export interface ConnectionStats {
   connectedTime: number;
   totalDownload: number;
   totalUpload: number;
+  mtu: number | null;
 }
 
 export interface ConnectionLog {